@@ -0,0 +1,193 @@
+//! Compares two `.anim` files for review, with `--diff <old.anim> <new.anim>`. Only the
+//! gameplay data is compared, not the images.
+
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt::{Display, Write},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use crate::{AnimationFileData, FrameData, Info};
+
+/// Summarizes what changed from `old` to `new`, one difference per line.
+pub(crate) fn diff_files(
+    old: impl AsRef<Path>,
+    new: impl AsRef<Path>,
+) -> Result<String, Box<dyn Error>> {
+    let old = read_info(old)?;
+    let new = read_info(new)?;
+    let lines = diff_info(&old, &new);
+    if lines.is_empty() {
+        return Ok("No differences\n".to_string());
+    }
+
+    let mut summary = String::new();
+    for line in lines {
+        writeln!(summary, "{line}")?;
+    }
+    Ok(summary)
+}
+
+/// Reads the info of an `.anim` file in y-up coordinates, so that files saved with different
+/// y axes compare by where their boxes are rather than by their numbers.
+fn read_info(path: impl AsRef<Path>) -> Result<Info, Box<dyn Error>> {
+    let file_data: AnimationFileData = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    Ok(file_data.info.into_y_up())
+}
+
+fn diff_info(old: &Info, new: &Info) -> Vec<String> {
+    let mut lines = vec![];
+    let mut field = |name: &str, old: &dyn Display, new: &dyn Display| {
+        let (old, new) = (old.to_string(), new.to_string());
+        if old != new {
+            lines.push(format!("{name}: {old} -> {new}"));
+        }
+    };
+    field("fps", &old.fps, &new.fps);
+    field(
+        "anchor-relative hitboxes",
+        &old.anchor_relative_hitboxes,
+        &new.anchor_relative_hitboxes,
+    );
+    field("scale hitboxes", &old.scale_hitboxes, &new.scale_hitboxes);
+    field("y axis", &old.y_axis.label(), &new.y_axis.label());
+    field("frame count", &old.frame_data.len(), &new.frame_data.len());
+
+    let ids = old
+        .hitboxes
+        .keys()
+        .chain(new.hitboxes.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for id in &ids {
+        match (old.hitboxes.get(id), new.hitboxes.get(id)) {
+            (Some(hitbox), None) => lines.push(format!("hitbox {id} \"{}\" removed", hitbox.desc)),
+            (None, Some(hitbox)) => lines.push(format!("hitbox {id} \"{}\" added", hitbox.desc)),
+            (Some(old), Some(new)) => {
+                if old.desc != new.desc {
+                    lines.push(format!(
+                        "hitbox {id} renamed: \"{}\" -> \"{}\"",
+                        old.desc, new.desc
+                    ));
+                }
+                if old.kind != new.kind {
+                    lines.push(format!(
+                        "hitbox {id} kind: {} -> {}",
+                        old.kind.label(),
+                        new.kind.label()
+                    ));
+                }
+                if old.tag != new.tag {
+                    lines.push(format!(
+                        "hitbox {id} tag: \"{}\" -> \"{}\"",
+                        old.tag, new.tag
+                    ));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    if old.hitbox_order != new.hitbox_order {
+        lines.push(format!(
+            "hitbox order: {:?} -> {:?}",
+            old.hitbox_order, new.hitbox_order
+        ));
+    }
+
+    for tag in &old.tags {
+        match new.tags.iter().find(|t| t.name == tag.name) {
+            None => lines.push(format!("tag \"{}\" removed", tag.name)),
+            Some(new_tag) if (new_tag.start, new_tag.end) != (tag.start, tag.end) => {
+                lines.push(format!(
+                    "tag \"{}\": frames {}-{} -> {}-{}",
+                    tag.name,
+                    tag.start + 1,
+                    tag.end + 1,
+                    new_tag.start + 1,
+                    new_tag.end + 1
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+    for tag in &new.tags {
+        if !old.tags.iter().any(|t| t.name == tag.name) {
+            lines.push(format!(
+                "tag \"{}\" added: frames {}-{}",
+                tag.name,
+                tag.start + 1,
+                tag.end + 1
+            ));
+        }
+    }
+
+    for (index, (old, new)) in old.frame_data.iter().zip(&new.frame_data).enumerate() {
+        diff_frame(&mut lines, index, old, new);
+    }
+    lines
+}
+
+fn diff_frame(lines: &mut Vec<String>, index: usize, old: &FrameData, new: &FrameData) {
+    let frame = index + 1;
+    let mut field = |name: &str, old: &dyn Display, new: &dyn Display| {
+        let (old, new) = (old.to_string(), new.to_string());
+        if old != new {
+            lines.push(format!("frame {frame} {name}: {old} -> {new}"));
+        }
+    };
+    field("delay", &old.delay, &new.delay);
+    field("repeat", &old.repeat, &new.repeat);
+    field("offset", &old.origin, &new.origin);
+    field("root motion", &old.root_motion, &new.root_motion);
+    field("flip x", &old.flip_x, &new.flip_x);
+    field("flip y", &old.flip_y, &new.flip_y);
+    field("scale", &old.scale, &new.scale);
+    field("rotation", &old.rotation, &new.rotation);
+    field(
+        "note",
+        &format!("\"{}\"", old.note),
+        &format!("\"{}\"", new.note),
+    );
+
+    let ids = old
+        .hitboxes
+        .keys()
+        .chain(new.hitboxes.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for id in ids {
+        match (old.hitboxes.get(&id), new.hitboxes.get(&id)) {
+            (Some(_), None) => lines.push(format!("frame {frame} hitbox {id} removed")),
+            (None, Some(hp)) => lines.push(format!(
+                "frame {frame} hitbox {id} added at {} size {}",
+                hp.pos, hp.size
+            )),
+            (Some(old), Some(new)) => {
+                if old.pos != new.pos {
+                    lines.push(format!(
+                        "frame {frame} hitbox {id} moved: {} -> {} (by {})",
+                        old.pos,
+                        new.pos,
+                        new.pos - old.pos
+                    ));
+                }
+                if old.size != new.size {
+                    lines.push(format!(
+                        "frame {frame} hitbox {id} resized: {} -> {}",
+                        old.size, new.size
+                    ));
+                }
+                if old.enabled != new.enabled {
+                    lines.push(format!(
+                        "frame {frame} hitbox {id} enabled: {} -> {}",
+                        old.enabled, new.enabled
+                    ));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+}
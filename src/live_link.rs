@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::{ui::UiState, EditorState, FrameData, FrameTag, Hitbox, Stages, YAxis};
+
+/// Port the live link listens on, on localhost only.
+pub(crate) const LIVE_LINK_PORT: u16 = 7878;
+
+/// How many unsent bytes a client may fall behind by before it is dropped.
+const MAX_UNSENT_BYTES: usize = 16 * 1024 * 1024;
+
+pub(crate) fn add_systems(app: &mut App) {
+    app.insert_resource(LiveLinkServer::default());
+    app.add_system(serve_live_link.in_set(Stages::Logic));
+}
+
+/// Streams the animation's gameplay data to connected clients as newline-delimited JSON,
+/// so a running game can hot-reload it.
+#[derive(Resource, Default)]
+struct LiveLinkServer {
+    listener: Option<TcpListener>,
+    clients: Vec<LiveLinkClient>,
+    sent_revision: Option<u64>,
+}
+
+struct LiveLinkClient {
+    stream: TcpStream,
+    /// Bytes not yet taken by the socket, so that messages always arrive whole.
+    unsent: Vec<u8>,
+}
+
+impl LiveLinkClient {
+    /// Writes as much of the queue as the socket takes without blocking.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.unsent.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if self.unsent.len() > MAX_UNSENT_BYTES {
+            return Err(io::Error::other("the client is not reading the updates"));
+        }
+        Ok(())
+    }
+}
+
+/// Everything but the images, in the same shape as the `.anim` format.
+#[derive(Serialize)]
+struct LiveLinkUpdate<'a> {
+    frame_data: Vec<FrameData>,
+    hitboxes: &'a HashMap<usize, Hitbox>,
+    hitbox_order: &'a [usize],
+    anchor_relative_hitboxes: bool,
+    scale_hitboxes: bool,
+    active_windows: HashMap<usize, Vec<[usize; 2]>>,
+    tags: &'a [FrameTag],
+    fps: u32,
+    y_axis: YAxis,
+}
+
+fn serve_live_link(
+    mut editor_state: ResMut<EditorState>,
+    mut ui_state: ResMut<UiState>,
+    mut server: ResMut<LiveLinkServer>,
+) {
+    if !editor_state.live_link_enabled {
+        if server.listener.is_some() {
+            *server = LiveLinkServer::default();
+        }
+        return;
+    }
+
+    if server.listener.is_none() {
+        let listener = TcpListener::bind(("127.0.0.1", LIVE_LINK_PORT))
+            .and_then(|listener| listener.set_nonblocking(true).map(|()| listener));
+        match listener {
+            Ok(listener) => server.listener = Some(listener),
+            Err(err) => {
+                // Turned off so that it isn't retried every frame.
+                editor_state.live_link_enabled = false;
+                ui_state.toast(format!(
+                    "Could not start live link on port {LIVE_LINK_PORT}: {err}"
+                ));
+                return;
+            }
+        }
+    }
+
+    let mut new_clients = vec![];
+    loop {
+        match server.listener.as_ref().unwrap().accept() {
+            // Writes must not stall the editor, so they are queued and flushed every frame.
+            Ok((stream, _)) => match stream.set_nonblocking(true) {
+                Ok(()) => new_clients.push(stream),
+                Err(err) => ui_state.toast(format!("Live link client dropped: {err}")),
+            },
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) => {
+                ui_state.toast(format!("Live link accept failed: {err}"));
+                break;
+            }
+        }
+    }
+
+    let changed = server.sent_revision != Some(editor_state.revision);
+    if changed || !new_clients.is_empty() {
+        let animation = &editor_state.current_animation;
+        let update = LiveLinkUpdate {
+            frame_data: animation
+                .timeline
+                .frames
+                .iter()
+                .map(|frame| frame.to_frame_data().in_y_axis(animation.y_axis))
+                .collect(),
+            hitboxes: &animation.hitboxes,
+            hitbox_order: &animation.hitbox_order,
+            anchor_relative_hitboxes: animation.anchor_relative_hitboxes,
+            scale_hitboxes: animation.scale_hitboxes,
+            active_windows: animation.active_windows(),
+            tags: &animation.tags,
+            fps: animation.fps,
+            y_axis: animation.y_axis,
+        };
+        let mut message = serde_json::to_vec(&update).unwrap();
+        message.push(b'\n');
+
+        if changed {
+            for client in &mut server.clients {
+                client.unsent.extend_from_slice(&message);
+            }
+            server.sent_revision = Some(editor_state.revision);
+        }
+        server
+            .clients
+            .extend(new_clients.into_iter().map(|stream| LiveLinkClient {
+                stream,
+                unsent: message.clone(),
+            }));
+    }
+
+    server.clients.retain_mut(|client| match client.flush() {
+        Ok(()) => true,
+        Err(err) => {
+            ui_state.toast(format!("Live link client dropped: {err}"));
+            false
+        }
+    });
+}
@@ -4,39 +4,54 @@
 #![feature(int_roundings)]
 #![feature(hash_drain_filter)]
 
+mod diff;
+#[cfg(feature = "live-link")]
+mod live_link;
+#[cfg(test)]
+mod tests;
 mod ui;
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     default::default,
+    error::Error,
     future::Future,
     io::Cursor,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use bevy::{
     app::AppExit,
-    input::keyboard::KeyboardInput,
+    asset::HandleId,
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseScrollUnit, MouseWheel},
+    },
     prelude::*,
-    render::render_resource::Extent3d,
+    render::render_resource::{Extent3d, TextureFormat},
     sprite::{Anchor, Sprite, SpriteBundle},
     text::TextStyle,
     ui::{JustifyContent, Size, Style, UiRect, Val},
     window::{PrimaryWindow, Window, WindowCloseRequested},
     DefaultPlugins,
 };
-use bevy_egui::EguiPlugin;
+use bevy_egui::{EguiContexts, EguiPlugin};
 use bevy_prototype_lyon::prelude::*;
 use futures::io::BufWriter;
-use image::{DynamicImage, ImageFormat};
+use image::{imageops::FilterType, DynamicImage, GenericImage, ImageFormat};
 use leafwing_input_manager::{
     prelude::{ActionState, DualAxis, InputManagerPlugin, InputMap},
     user_input::{InputKind, Modifier},
     Actionlike, InputManagerBundle,
 };
 use rfd::FileHandle;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ui::UiState;
 
@@ -68,16 +83,102 @@ enum Input2 {
     PrevFrame,
     NextFrame,
     TogglePlayback,
+    CreateHitboxAtCursor,
+    CancelDrag,
+    ToggleSelectedHitbox,
+    ToggleSelectedHurtbox,
+    SelectPrevHitbox,
+    SelectNextHitbox,
+    PasteFrame,
+    TogglePreview,
+    CommandPalette,
+    SelectAllHitboxes,
+    DeselectAllHitboxes,
+    DuplicateFrame,
+    ShowShortcuts,
+    FitView,
+    ContextMenu,
+}
+
+impl Input2 {
+    /// The section and description the shortcuts window lists this action under.
+    fn help(&self) -> (&'static str, &'static str) {
+        match self {
+            Input2::LeftClick => ("Canvas", "Select, move or draw with the current tool"),
+            Input2::ShiftLeftClick => ("Canvas", "Resize the hitbox under the cursor"),
+            Input2::Pan => ("Canvas", "Pan the view while held"),
+            Input2::CancelDrag => ("Canvas", "Cancel the current drag"),
+            Input2::ContextMenu => ("Canvas", "Open the menu for the hitbox under the cursor"),
+            Input2::ToolSelect => ("Tools", "Select"),
+            Input2::ToolMoveAnchor => ("Tools", "Move anchor"),
+            Input2::ToolCreateHitbox => ("Tools", "Create hitbox"),
+            Input2::ToolCreateHurtbox => ("Tools", "Create hurtbox"),
+            Input2::ToolCreateCollisionbox => ("Tools", "Create collision box"),
+            Input2::New => ("File", "New animation"),
+            Input2::Open => ("File", "Open"),
+            Input2::Save => ("File", "Save"),
+            Input2::SaveAs => ("File", "Save as"),
+            Input2::Undo => ("Edit", "Undo"),
+            Input2::Redo => ("Edit", "Redo"),
+            Input2::DeleteSelected => ("Edit", "Disable the selected hitbox on this frame"),
+            Input2::AddFrame => ("Frames", "Add frames from files"),
+            Input2::PasteFrame => ("Frames", "Paste a frame from the clipboard"),
+            Input2::DuplicateFrame => ("Frames", "Duplicate the current frame"),
+            Input2::DeleteFrame => ("Frames", "Delete the current frame"),
+            Input2::PrevFrame => ("Frames", "Previous frame"),
+            Input2::NextFrame => ("Frames", "Next frame"),
+            Input2::TogglePlayback => ("Frames", "Play or pause"),
+            Input2::CreateHitboxAtCursor => ("Hitboxes", "Create a hitbox at the cursor"),
+            Input2::ToggleSelectedHitbox => {
+                ("Hitboxes", "Toggle the selected hitbox on this frame")
+            }
+            Input2::ToggleSelectedHurtbox => (
+                "Hitboxes",
+                "Toggle the selected box between hurtbox and hitbox",
+            ),
+            Input2::SelectPrevHitbox => ("Hitboxes", "Select the previous hitbox"),
+            Input2::SelectNextHitbox => ("Hitboxes", "Select the next hitbox"),
+            Input2::SelectAllHitboxes => ("Hitboxes", "Select all hitboxes"),
+            Input2::DeselectAllHitboxes => ("Hitboxes", "Deselect all hitboxes"),
+            Input2::TogglePreview => ("View", "Toggle preview mode"),
+            Input2::CommandPalette => ("View", "Command palette"),
+            Input2::ShowShortcuts => ("View", "Keyboard shortcuts"),
+            Input2::FitView => ("View", "Fit the view to the current frame"),
+        }
+    }
 }
 
 fn main() {
-    println!("{}", InteractionLock::All < InteractionLock::Playback);
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(index) = args.iter().position(|arg| arg == "--emit-schema") {
+        emit_schema(args.get(index + 1));
+        return;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--diff") {
+        let (Some(old), Some(new)) = (args.get(index + 1), args.get(index + 2)) else {
+            eprintln!("Usage: --diff <old.anim> <new.anim>");
+            std::process::exit(2);
+        };
+        match diff::diff_files(old, new) {
+            Ok(summary) => print!("{summary}"),
+            Err(err) => {
+                eprintln!("Could not compare {old} and {new}: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let safe_mode = args.iter().any(|arg| arg == "--safe");
+    if safe_mode {
+        println!("Starting in safe mode: workspace presets and the file argument are not loaded");
+    }
 
     let mut app = App::new();
-    app.insert_resource(EditorState::new())
+    app.insert_resource(EditorState::new(safe_mode))
         .insert_non_send_resource(PendingFileDialog { action: None })
         .insert_resource(Msaa::Off)
-        .insert_resource(LastMousePos(default()))
+        .insert_resource(LastMousePos(None))
         .insert_resource(MouseDelta(default()))
         .add_plugins(
             DefaultPlugins
@@ -88,17 +189,31 @@ fn main() {
                 }),
         )
         .add_plugin(EguiPlugin)
+        .add_plugin(FrameTimeDiagnosticsPlugin)
         .add_plugin(ShapePlugin)
         .add_plugin(InputManagerPlugin::<Input2>::default())
         .configure_set(Stages::Logic.before(Stages::Ui))
         .add_startup_system(start)
+        .add_startup_system(open_workspace_presets)
+        .add_startup_system(open_file_from_args)
         .add_systems(
             (
                 mouse_delta.before(mouse_interaction),
                 poll_pending_file_dialog,
                 mouse_interaction,
                 keyboard_interaction,
+                run_editor_commands.after(keyboard_interaction),
                 render.after(mouse_interaction),
+                update_grid.after(render),
+                render_reference_frame,
+                render_lightbox,
+                render_marquee.after(mouse_interaction),
+                sync_camera.after(mouse_interaction),
+                capture_canvas.after(render).after(render_reference_frame),
+                update_window_title,
+                update_trim_preview.after(render),
+                sync_playback_rate,
+                sync_performance_diagnostics,
                 exit_system,
                 on_close,
             )
@@ -108,13 +223,83 @@ fn main() {
         .unwrap()
         .add_system(animator);
     ui::add_systems(&mut app);
+    #[cfg(feature = "live-link")]
+    live_link::add_systems(&mut app);
 
     app.run();
 }
 
+/// Writes the JSON Schema of the `.anim` format to `path`, or to stdout if no path is given.
+fn emit_schema(path: Option<&String>) {
+    let schema = schemars::schema_for!(AnimationFileData);
+    let schema = serde_json::to_string_pretty(&schema).unwrap();
+    match path {
+        Some(path) => {
+            if let Err(err) = std::fs::write(path, schema) {
+                eprintln!("Could not write the schema to {path}: {err}");
+                std::process::exit(1);
+            }
+        }
+        None => println!("{schema}"),
+    }
+}
+
+/// Loads the saved workspace presets, unless in safe mode.
+fn open_workspace_presets(mut editor_state: ResMut<EditorState>, mut ui_state: ResMut<UiState>) {
+    if editor_state.safe_mode {
+        return;
+    }
+    match load_workspace_presets() {
+        Ok(presets) => editor_state.workspace_presets = presets,
+        Err(err) => {
+            println!("{err}");
+            ui_state.toast(err);
+        }
+    }
+}
+
+fn open_file_from_args(
+    mut editor_state: ResMut<EditorState>,
+    mut ui_state: ResMut<UiState>,
+    mut assets: ResMut<Assets<Image>>,
+) {
+    if editor_state.safe_mode {
+        ui_state.toast("Safe mode: workspace presets and the file argument were not loaded");
+        return;
+    }
+    let Some(path) = std::env::args().skip(1).find(|arg| !arg.starts_with("--")) else {
+        return;
+    };
+
+    if let Err(err) = editor_state.load(&path, &mut assets) {
+        println!("Could not open {path}: {err}");
+        ui_state.toast(format!("Could not open {path}: {err}"));
+    }
+}
+
 #[derive(Component)]
 struct MotionMarker;
 
+#[derive(Component)]
+struct GridLine;
+
+#[derive(Component)]
+struct Marquee;
+
+#[derive(Component)]
+struct TrimPreviewOutline;
+
+#[derive(Component)]
+struct TrimContentOutline;
+
+/// Translucent copy of the pinned reference frame, drawn over the current one.
+#[derive(Component)]
+struct ReferenceSprite;
+
+/// Guide image drawn behind everything, see [`Lightbox`].
+#[derive(Component)]
+struct LightboxSprite;
+
 fn start(
     mut commands: Commands,
     mut editor_state: ResMut<EditorState>,
@@ -122,6 +307,7 @@ fn start(
 ) {
     let mut input_map = InputMap::default();
     input_map.insert(MouseButton::Left, Input2::LeftClick);
+    input_map.insert(MouseButton::Right, Input2::ContextMenu);
     input_map.insert_modified(Modifier::Shift, MouseButton::Left, Input2::ShiftLeftClick);
     input_map.insert(KeyCode::Space, Input2::Pan);
     input_map.insert(KeyCode::Q, Input2::ToolSelect);
@@ -155,6 +341,34 @@ fn start(
     input_map.insert(KeyCode::A, Input2::PrevFrame);
     input_map.insert(KeyCode::D, Input2::NextFrame);
     input_map.insert(KeyCode::K, Input2::TogglePlayback);
+    input_map.insert(KeyCode::C, Input2::CreateHitboxAtCursor);
+    input_map.insert(KeyCode::Escape, Input2::CancelDrag);
+    input_map.insert(KeyCode::X, Input2::ToggleSelectedHitbox);
+    input_map.insert(KeyCode::H, Input2::ToggleSelectedHurtbox);
+    input_map.insert(KeyCode::BracketLeft, Input2::SelectPrevHitbox);
+    input_map.insert(KeyCode::BracketRight, Input2::SelectNextHitbox);
+    input_map.insert(KeyCode::P, Input2::TogglePreview);
+    input_map.insert_modified(Modifier::Control, KeyCode::P, Input2::CommandPalette);
+    input_map.insert_modified(Modifier::Control, KeyCode::A, Input2::SelectAllHitboxes);
+    input_map.insert_modified(Modifier::Control, KeyCode::D, Input2::DuplicateFrame);
+    input_map.insert_modified(Modifier::Shift, KeyCode::Slash, Input2::ShowShortcuts);
+    input_map.insert(KeyCode::Home, Input2::FitView);
+    input_map.insert_chord(
+        [
+            InputKind::from(Modifier::Control),
+            Modifier::Shift.into(),
+            KeyCode::A.into(),
+        ],
+        Input2::DeselectAllHitboxes,
+    );
+    input_map.insert_chord(
+        [
+            InputKind::from(Modifier::Control),
+            Modifier::Shift.into(),
+            KeyCode::V.into(),
+        ],
+        Input2::PasteFrame,
+    );
 
     commands.spawn(InputManagerBundle::<Input2> {
         action_state: default(),
@@ -163,7 +377,7 @@ fn start(
 
     commands.spawn(Camera2dBundle {
         projection: OrthographicProjection {
-            scale: 0.1,
+            scale: DEFAULT_ZOOM,
             ..default()
         },
         ..default()
@@ -171,6 +385,55 @@ fn start(
 
     ui::build_ui(&mut commands);
 
+    commands.spawn((
+        ShapeBundle {
+            transform: Transform {
+                translation: Vec3 {
+                    z: 2.0,
+                    ..default()
+                },
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Fill::color(Color::WHITE.with_a(0.1)),
+        Stroke::new(Color::WHITE, 0.1),
+        Marquee,
+    ));
+
+    commands.spawn((
+        ShapeBundle {
+            transform: Transform {
+                translation: Vec3 {
+                    z: 2.0,
+                    ..default()
+                },
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Stroke::new(Color::CYAN, 0.1),
+        TrimPreviewOutline,
+    ));
+
+    commands.spawn((
+        ShapeBundle {
+            transform: Transform {
+                translation: Vec3 {
+                    z: 2.0,
+                    ..default()
+                },
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Stroke::new(Color::ORANGE, 0.1),
+        TrimContentOutline,
+    ));
+
     let mut shape = shapes::Polygon::default();
     shape.points = vec![
         Vec2::new(0.0, 1.0),
@@ -205,6 +468,31 @@ fn start(
         ..default()
     });
 
+    commands.spawn((
+        SpriteBundle {
+            texture: Handle::default(),
+            sprite: Sprite {
+                anchor: Anchor::TopLeft,
+                color: Color::rgba(1.0, 0.4, 0.8, 0.5),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ReferenceSprite,
+    ));
+
+    commands.spawn((
+        SpriteBundle {
+            texture: Handle::default(),
+            transform: Transform::from_xyz(0.0, 0.0, -1.0),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        LightboxSprite,
+    ));
+
     let line = shapes::Line(
         Vec2 {
             x: -10000.0,
@@ -218,6 +506,7 @@ fn start(
             ..default()
         },
         Stroke::new(Color::BLUE, 0.15),
+        GridLine,
     ));
 
     for i in -10..=10 {
@@ -240,6 +529,7 @@ fn start(
                 ..default()
             },
             Stroke::new(Color::rgba(0.0, 0.0, 0.0, 0.5), 0.1),
+            GridLine,
         ));
     }
 
@@ -256,6 +546,7 @@ fn start(
             ..default()
         },
         Stroke::new(Color::RED, 0.15),
+        GridLine,
     ));
 
     for i in -10..=10 {
@@ -278,197 +569,212 @@ fn start(
                 ..default()
             },
             Stroke::new(Color::rgba(0.0, 0.0, 0.0, 0.5), 0.1),
+            GridLine,
         ));
     }
 }
 
-fn load(path: impl AsRef<Path>, assets: &mut Assets<Image>) -> Animation {
-    let animation_file_data: AnimationFileData =
-        serde_json::from_reader(std::fs::File::open(path).unwrap()).unwrap();
-
-    let cell_width = animation_file_data.info.cell_width as u32;
-    let cell_height = animation_file_data.info.cell_height as u32;
-    let cols = animation_file_data.info.columns as u32;
-    let frame_count = animation_file_data.info.frame_count as u32;
+/// Returns the number of columns of the grid `pack_spritesheet` lays `cell_count` cells out in.
+fn spritesheet_columns(
+    cell_count: usize,
+    cell_width: u32,
+    cell_height: u32,
+    max_cols: usize,
+    max_rows: usize,
+) -> u32 {
+    let mut cols = cell_count.min(max_cols);
 
-    let image = image::load_from_memory(&animation_file_data.spritesheet).unwrap();
+    for c in (1..=cell_count.min(max_cols)).rev() {
+        let r = cell_count.div_ceil(c);
 
-    let mut frames = vec![];
-
-    for i in 0..frame_count {
-        let x = i % cols;
-        let y = i / cols;
+        let w = c * cell_width as usize;
+        let h = r * cell_height as usize;
 
-        let handle = assets.add(Image::from_dynamic(
-            image.crop_imm(x * cell_width, y * cell_height, cell_width, cell_height),
-            true,
-        ));
-        let frame_info = &animation_file_data.info.frame_data[i as usize];
-        println!("{}", frame_info.origin);
-        let offset = frame_info.origin;
-        println!("{}", offset);
-        let root_motion = frame_info.root_motion;
-        let hitboxes = frame_info.hitboxes.clone();
-        let delay = frame_info.delay;
-
-        frames.push(Frame {
-            image: handle,
-            offset,
-            root_motion,
-            delay,
-            hitboxes,
-        });
+        if h > w || r > max_rows {
+            break;
+        }
+        cols = c;
     }
 
-    Animation {
-        timeline: Timeline { frames },
-        hitboxes: animation_file_data.info.hitboxes.clone(),
-    }
+    cols.max(1) as u32
 }
 
-#[derive(Serialize, Deserialize)]
-struct ImageHandle {
-    path: String,
-    #[serde(skip)]
-    image: Handle<Image>,
-}
+/// Packs equally sized cells into a grid that is as close to square as possible,
+/// without exceeding `max_cols` columns or `max_rows` rows.
+fn pack_spritesheet(
+    cells: &[&DynamicImage],
+    cell_width: u32,
+    cell_height: u32,
+    max_cols: usize,
+    max_rows: usize,
+) -> (DynamicImage, u32) {
+    let cols = spritesheet_columns(cells.len(), cell_width, cell_height, max_cols, max_rows);
+    let rows = cells.len().div_ceil(cols as usize) as u32;
+
+    let mut spritesheet = DynamicImage::new_rgba8(cols * cell_width, rows * cell_height);
+    let spritesheet_pixels = spritesheet.as_mut_rgba8().unwrap();
+
+    for ix in 0..cols {
+        for iy in 0..rows {
+            let index = (iy * cols + ix) as usize;
+            if index >= cells.len() {
+                continue;
+            }
 
-#[derive(Resource)]
-struct EditorState {
-    current_animation: Animation,
-    current_basepath: Option<String>,
-    current_frame: usize,
-    action_list: Vec<Action>,
-    undo_depth: usize,
-    drag_starting_pos: Option<Vec2>,
-    selected_tool: Tool,
-    currently_selected_box: Option<usize>,
-    has_saved: bool,
-    action_after_save: Option<Box<dyn FnOnce(&mut EditorState) + Send + Sync>>,
-    exit_now: bool,
-    with_pfd: Option<Box<dyn FnOnce(&mut PendingFileDialog) + Send + Sync>>,
-    animation_running: bool,
-    frames_since_last_frame: usize,
-    interaction_lock: InteractionLock,
-    always_show_root_motion: bool,
-    show_hitboxes: bool,
-}
+            let original_pixels = cells[index].as_rgba8().unwrap();
+            for lx in 0..cell_width {
+                for ly in 0..cell_height {
+                    let tx = ix * cell_width + lx;
+                    let ty = iy * cell_height + ly;
 
-impl EditorState {
-    fn new() -> Self {
-        Self {
-            current_animation: Animation::new(),
-            current_basepath: None,
-            current_frame: 0,
-            action_list: vec![],
-            undo_depth: 0,
-            drag_starting_pos: None,
-            selected_tool: Tool::Select,
-            currently_selected_box: None,
-            has_saved: true,
-            action_after_save: None,
-            exit_now: false,
-            with_pfd: None,
-            animation_running: false,
-            frames_since_last_frame: 0,
-            interaction_lock: InteractionLock::None,
-            always_show_root_motion: false,
-            show_hitboxes: true,
+                    spritesheet_pixels[(tx, ty)] = original_pixels[(lx, ly)];
+                }
+            }
         }
     }
 
-    fn confirm_if_unsaved(
-        &mut self,
-        ui_state: &mut UiState,
-        action: impl FnOnce(&mut Self) + Send + Sync + 'static,
-        unlock_on_non_cancel: bool,
-    ) {
-        if self.has_saved {
-            action(self);
-        } else {
-            self.animation_running = false;
-            self.frames_since_last_frame = 0;
-            self.interaction_lock.lock_all();
-            ui_state.show_save_menu = true;
-            ui_state.save_menu_unlock_on_non_cancel = unlock_on_non_cancel;
-            self.action_after_save = Some(Box::new(action));
+    (spritesheet, cols)
+}
+
+/// Packs differently sized `cells` into rows, left to right. Rows wrap at `max_width`, and a new
+/// page is started when a page would grow taller than `max_page_height`. Empty cells, from fully
+/// transparent frames, get a transparent 1x1 rect so that every frame has an image.
+fn pack_tight(
+    cells: &[&DynamicImage],
+    max_width: u32,
+    max_page_height: Option<u32>,
+) -> (Vec<DynamicImage>, Vec<FrameRect>) {
+    let total_area = cells
+        .iter()
+        .map(|cell| cell.width() as u64 * cell.height() as u64)
+        .sum::<u64>();
+    let widest = cells.iter().map(|cell| cell.width()).max().unwrap_or(0);
+    let page_width = ((total_area as f64).sqrt().ceil() as u32)
+        .min(max_width)
+        .max(widest)
+        .max(1);
+
+    let mut rects = vec![];
+    let mut page_sizes = vec![(0, 0)];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for cell in cells {
+        let (width, height) = (cell.width().max(1), cell.height().max(1));
+        if x > 0 && x + width > page_width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        if y > 0 && max_page_height.is_some_and(|max| y + height > max) {
+            page_sizes.push((0, 0));
+            (x, y, row_height) = (0, 0, 0);
         }
+
+        let page = page_sizes.len() - 1;
+        rects.push(FrameRect {
+            page,
+            x,
+            y,
+            width,
+            height,
+        });
+        let size = &mut page_sizes[page];
+        *size = (size.0.max(x + width), size.1.max(y + height));
+        x += width;
+        row_height = row_height.max(height);
     }
 
-    fn save(&mut self, pending_file_dialog: &mut PendingFileDialog, assets: &Assets<Image>) {
-        if let Some(path) = self.current_basepath.clone() {
-            self.save_to(path, assets);
-        } else {
-            let future = rfd::AsyncFileDialog::new()
-                .add_filter("anim", &["anim"])
-                .save_file();
-            self.interaction_lock.lock_all();
-            self.animation_running = false;
-            self.frames_since_last_frame = 0;
-            pending_file_dialog.action = Some(FileAction::Save(Box::pin(future)));
-        }
+    let mut pages = page_sizes
+        .into_iter()
+        .map(|(width, height)| DynamicImage::new_rgba8(width.max(1), height.max(1)))
+        .collect::<Vec<_>>();
+    for (cell, rect) in cells.iter().zip(&rects) {
+        pages[rect.page].copy_from(*cell, rect.x, rect.y).unwrap();
     }
 
-    fn save_to(&mut self, path: impl AsRef<Path>, assets: &Assets<Image>) {
-        let mut images = self
-            .current_animation
-            .timeline
-            .frames
-            .iter()
-            .map(|ih| {
-                let img = assets.get(&ih.image).unwrap();
-                let img = img.clone().try_into_dynamic().unwrap();
-                let offset = ih.offset;
-                let root_motion = ih.root_motion;
-                let hitboxes = ih.hitboxes.clone();
-                println!("{}", offset);
-                (img, offset, root_motion, hitboxes, ih.delay)
-            })
-            .collect::<Vec<_>>();
+    (pages, rects)
+}
 
-        let image_count = images.len();
+/// Returns the `(left, top, width, height)` of the pixels of `image` with an alpha above
+/// `alpha_threshold`. An image without any has a width and height of 0.
+fn content_bounds(image: &DynamicImage, alpha_threshold: u8) -> (u32, u32, u32, u32) {
+    let pixels = image.as_rgba8().unwrap();
+
+    let mut left = pixels.width();
+    let mut right = 0;
+    let mut top = pixels.height();
+    let mut bottom = 0;
+
+    for x in 0..pixels.width() {
+        for y in 0..pixels.height() {
+            let has_pixel = pixels[(x, y)][3] > alpha_threshold;
+
+            if has_pixel {
+                left = x.min(left);
+                right = x;
+                top = y.min(top);
+                bottom = y.max(bottom);
+            }
+        }
+    }
 
-        let mut image_bb_width = 0;
-        let mut image_bb_height = 0;
+    let (width, height) = if right < left {
+        (0, 0)
+    } else {
+        (right - left + 1, (bottom - top + 1))
+    };
 
-        for (image, offset, _, _, _) in &mut images {
-            let pixels = image.as_rgba8().unwrap();
+    (left, top, width, height)
+}
 
-            let mut left = pixels.width();
-            let mut right = 0;
-            let mut top = pixels.height();
-            let mut bottom = 0;
+/// Trims, pads (unless tight packing) and packs the frames of `animation` into a spritesheet and writes it to `path`.
+/// Returns the size that exceeded the max texture size, if any.
+fn write_animation(
+    path: impl AsRef<Path>,
+    animation: &Animation,
+    assets: &Assets<Image>,
+    settings: &ExportSettings,
+    editor_view: Option<EditorView>,
+) -> Result<Option<(u32, u32)>, Box<dyn Error>> {
+    let mut oversized_atlas = None;
+
+    let collapsed;
+    let animation = if settings.collapse_held_frames {
+        collapsed = animation.collapse_held_frames();
+        &collapsed
+    } else {
+        animation
+    };
 
-            for x in 0..pixels.width() {
-                for y in 0..pixels.height() {
-                    let has_pixel = pixels[(x, y)][3] != 0;
+    let mut images = animation
+        .timeline
+        .frames
+        .iter()
+        .map(|ih| {
+            let img = assets
+                .get(&ih.image)
+                .ok_or("a frame's image is not loaded")?;
+            let img = img.clone().try_into_dynamic()?;
+            Ok((img, ih.to_frame_data().in_y_axis(animation.y_axis)))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
 
-                    if has_pixel {
-                        left = x.min(left);
-                        right = x;
-                        top = y.min(top);
-                        bottom = y.max(bottom);
-                    }
-                }
-            }
+    let image_count = images.len();
 
-            let (width, height) = if right < left {
-                (0, 0)
-            } else {
-                (right - left + 1, (bottom - top + 1))
-            };
+    let mut image_bb_width = 0;
+    let mut image_bb_height = 0;
 
-            println!("{width}, {height}");
+    for (image, FrameData { origin: offset, .. }) in &mut images {
+        let (left, top, width, height) = content_bounds(image, settings.alpha_threshold);
 
-            *image = image.crop_imm(left, top, width, height);
+        *image = image.crop_imm(left, top, width, height);
 
-            *offset = Vec2::new(offset.x - left as f32, offset.y - top as f32);
-            println!("{offset}");
+        *offset = Vec2::new(offset.x - left as f32, offset.y - top as f32);
 
-            image_bb_width = image_bb_width.max(width);
-            image_bb_height = image_bb_height.max(height);
-        }
-        for (image, offset, _, _, _) in &mut images {
+        image_bb_width = image_bb_width.max(width);
+        image_bb_height = image_bb_height.max(height);
+    }
+    if !settings.tight_packing {
+        for (image, FrameData { origin: offset, .. }) in &mut images {
             let diff_x = image_bb_width - image.width();
             let diff_y = image_bb_height - image.height();
 
@@ -477,13 +783,6 @@ impl EditorState {
             let pad_top = diff_y / 2;
             let pad_bot = diff_y - pad_top;
 
-            println!(
-                "bb: {image_bb_width}, {image_bb_height} | width: {}, {}",
-                image.width(),
-                image.height()
-            );
-            println!("left: {pad_left}, right: {pad_right}, top: {pad_top}, bot: {pad_bot}");
-
             let mut expanded_image = DynamicImage::new_rgba8(image_bb_width, image_bb_height);
             let pixels = expanded_image.as_mut_rgba8().unwrap();
             let orig_pixels = image.as_rgba8().unwrap();
@@ -505,151 +804,2148 @@ impl EditorState {
             *image = expanded_image;
             *offset += Vec2::new(pad_left as _, pad_top as _);
         }
+    }
 
-        // for (index, (img, offset, delay)) in expanded_images.iter().enumerate() {
-        //     let mut path = PathBuf::from(path.as_ref());
-        //     let file_name = path.file_name().unwrap();
-        //     let new_file_name = format!("{}.{index}.png", file_name.to_string_lossy());
-        //     path.set_file_name(new_file_name);
-        //     img.save(path).unwrap();
-        // }
-
-        let mut cols = images.len();
-
-        for c in (1..=images.len()).rev() {
-            let r = images.len().div_ceil(c);
+    let cells = images.iter().map(|(image, _)| image).collect::<Vec<_>>();
 
-            let w = c * image_bb_width as usize;
-            let h = r * image_bb_height as usize;
+    let mut pages = vec![];
+    let mut rects = vec![None; images.len()];
+    let mut cols = 0;
 
-            if h > w {
-                break;
+    if settings.tight_packing {
+        let max_page_height = settings
+            .multi_page_atlas
+            .then_some(settings.max_texture_size);
+        let (tight_pages, tight_rects) =
+            pack_tight(&cells, settings.max_texture_size, max_page_height);
+        for page in &tight_pages {
+            if page.width() > settings.max_texture_size || page.height() > settings.max_texture_size
+            {
+                oversized_atlas = Some((page.width(), page.height()));
             }
-            cols = c;
         }
-
-        let cols = cols as u32;
+        pages = tight_pages;
+        rects = tight_rects.into_iter().map(Some).collect();
+    } else {
+        let max_cols = (settings.max_texture_size / image_bb_width.max(1)).max(1) as usize;
+        let max_rows = (settings.max_texture_size / image_bb_height.max(1)).max(1) as usize;
+
+        cols = spritesheet_columns(
+            cells.len(),
+            image_bb_width,
+            image_bb_height,
+            usize::MAX,
+            usize::MAX,
+        );
         let rows = images.len().div_ceil(cols as usize) as u32;
 
-        let mut spritesheet =
-            DynamicImage::new_rgba8(cols as u32 * image_bb_width, rows as u32 * image_bb_height);
-        let spritesheet_pixels = spritesheet.as_mut_rgba8().unwrap();
-
-        for ix in 0..cols {
-            for iy in 0..rows {
-                let index = (iy * cols + ix) as usize;
-                if index as usize >= images.len() {
-                    continue;
+        let atlas_width = cols * image_bb_width;
+        let atlas_height = rows * image_bb_height;
+        let oversized =
+            atlas_width > settings.max_texture_size || atlas_height > settings.max_texture_size;
+
+        if oversized && settings.multi_page_atlas {
+            let cells_per_page = max_cols * max_rows;
+            for (page, chunk) in cells.chunks(cells_per_page).enumerate() {
+                let (page_sheet, page_cols) =
+                    pack_spritesheet(chunk, image_bb_width, image_bb_height, max_cols, max_rows);
+                for i in 0..chunk.len() {
+                    rects[page * cells_per_page + i] = Some(FrameRect {
+                        page,
+                        x: (i as u32 % page_cols) * image_bb_width,
+                        y: (i as u32 / page_cols) * image_bb_height,
+                        width: image_bb_width,
+                        height: image_bb_height,
+                    });
                 }
+                pages.push(page_sheet);
+            }
 
-                let original_pixels = images[index].0.as_rgba8().unwrap();
-                for lx in 0..image_bb_width {
-                    for ly in 0..image_bb_height {
-                        let tx = ix * image_bb_width + lx;
-                        let ty = iy * image_bb_height + ly;
-
-                        spritesheet_pixels[(tx, ty)] = original_pixels[(lx, ly)];
-                    }
-                }
+            if image_bb_width > settings.max_texture_size
+                || image_bb_height > settings.max_texture_size
+            {
+                oversized_atlas = Some((image_bb_width, image_bb_height));
+            }
+        } else {
+            if oversized {
+                oversized_atlas = Some((atlas_width, atlas_height));
             }
+            let (spritesheet, _) = pack_spritesheet(
+                &cells,
+                image_bb_width,
+                image_bb_height,
+                usize::MAX,
+                usize::MAX,
+            );
+            pages.push(spritesheet);
         }
+    }
 
-        // spritesheet
-        //     .save(format!("{}.all.png", path.as_ref().to_string_lossy()))
-        //     .unwrap();
+    let frame_data = Info {
+        cell_width: image_bb_width as _,
+        cell_height: image_bb_height as _,
+        columns: cols as _,
+        frame_count: images.len(),
+        frame_data: images
+            .into_iter()
+            .zip(rects)
+            .map(|((_, frame_data), rect)| FrameData { rect, ..frame_data })
+            .collect(),
+        hitboxes: animation.hitboxes.clone(),
+        hitbox_order: animation.hitbox_order.clone(),
+        anchor_relative_hitboxes: animation.anchor_relative_hitboxes,
+        scale_hitboxes: animation.scale_hitboxes,
+        active_windows: animation.active_windows(),
+        tags: animation.tags.clone(),
+        metadata: animation.metadata.clone(),
+        fps: animation.fps,
+        y_axis: animation.y_axis,
+    };
 
-        let frame_data = Info {
-            cell_width: image_bb_width as _,
-            cell_height: image_bb_height as _,
-            columns: cols as _,
-            frame_count: images.len(),
-            frame_data: images
-                .into_iter()
-                .map(|(_, offset, root_motion, hitboxes, delay)| FrameData {
-                    delay,
-                    origin: offset,
-                    root_motion,
-                    hitboxes,
-                })
-                .collect(),
-            hitboxes: self.current_animation.hitboxes.clone(),
-        };
+    // serde_json::to_writer_pretty(
+    //     std::fs::File::create(format!("{}.json", path.as_ref().to_string_lossy())).unwrap(),
+    //     &frame_data,
+    // )
+    // .unwrap();
+
+    let mut pages = pages
+        .into_iter()
+        .map(|page| {
+            let mut bytes = vec![];
+            let mut cursor = Cursor::new(&mut bytes);
+            page.write_to(&mut cursor, ImageFormat::Png)?;
+            Ok(bytes)
+        })
+        .collect::<Result<Vec<_>, image::ImageError>>()?
+        .into_iter();
+
+    let animation_file_data = AnimationFileData {
+        spritesheet: pages.next().unwrap(),
+        extra_pages: pages.map(Spritesheet).collect(),
+        info: frame_data,
+        editor_view,
+    };
 
-        // serde_json::to_writer_pretty(
-        //     std::fs::File::create(format!("{}.json", path.as_ref().to_string_lossy())).unwrap(),
-        //     &frame_data,
-        // )
-        // .unwrap();
+    serde_json::to_writer_pretty(
+        std::fs::File::create(path.as_ref().to_string_lossy().as_ref())?,
+        &animation_file_data,
+    )?;
+    // std::fs::write(
+    //     format!("{}.anim.bincode", path.as_ref().to_string_lossy()),
+    //     bincode::serialize(&animation_file_data).unwrap(),
+    // )
+    // .unwrap();
+
+    Ok(oversized_atlas)
+}
 
-        let mut bytes = vec![];
-        let mut cursor = Cursor::new(&mut bytes);
-        spritesheet.write_to(&mut cursor, ImageFormat::Png).unwrap();
+/// Crops `image` like [`DynamicImage::crop_imm`], but fails instead of clamping when the rect
+/// does not lie within it.
+fn crop_checked(
+    image: &DynamicImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, Box<dyn Error>> {
+    let fits =
+        |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+    if !fits(x, width, image.width()) || !fits(y, height, image.height()) {
+        return Err(format!(
+            "the rect {width}x{height} at ({x}, {y}) lies outside of the {}x{} spritesheet",
+            image.width(),
+            image.height()
+        )
+        .into());
+    }
+    Ok(image.crop_imm(x, y, width, height))
+}
 
-        let animation_file_data = AnimationFileData {
-            spritesheet: bytes,
-            info: frame_data,
-        };
+fn load(
+    path: impl AsRef<Path>,
+    assets: &mut Assets<Image>,
+) -> Result<(Animation, Option<EditorView>), Box<dyn Error>> {
+    let mut animation_file_data: AnimationFileData =
+        serde_json::from_reader(std::fs::File::open(path)?)?;
+    animation_file_data.info = animation_file_data.info.into_y_up();
 
-        serde_json::to_writer_pretty(
-            std::fs::File::create(path.as_ref().to_string_lossy().as_ref()).unwrap(),
-            &animation_file_data,
-        )
-        .unwrap();
-        // std::fs::write(
-        //     format!("{}.anim.bincode", path.as_ref().to_string_lossy()),
-        //     bincode::serialize(&animation_file_data).unwrap(),
-        // )
-        // .unwrap();
+    let cell_width = animation_file_data.info.cell_width as u32;
+    let cell_height = animation_file_data.info.cell_height as u32;
+    let cols = animation_file_data.info.columns as u32;
+    let frame_count = animation_file_data.info.frame_count as u32;
+
+    let pages = std::iter::once(&animation_file_data.spritesheet)
+        .chain(animation_file_data.extra_pages.iter().map(|page| &page.0))
+        .map(|bytes| image::load_from_memory(bytes))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut frames = vec![];
+
+    for i in 0..frame_count {
+        let frame_info = animation_file_data
+            .info
+            .frame_data
+            .get(i as usize)
+            .ok_or_else(|| format!("frame {i} has no frame data"))?;
+
+        let cell = if let Some(rect) = frame_info.rect {
+            let page = pages.get(rect.page).ok_or_else(|| {
+                format!("frame {i} is on page {}, which does not exist", rect.page)
+            })?;
+            crop_checked(page, rect.x, rect.y, rect.width, rect.height)?
+        } else {
+            if cols == 0 {
+                return Err("the spritesheet has no columns".into());
+            }
+            let x = i % cols;
+            let y = i / cols;
+            crop_checked(
+                &pages[0],
+                x * cell_width,
+                y * cell_height,
+                cell_width,
+                cell_height,
+            )?
+        };
+
+        let handle = assets.add(Image::from_dynamic(cell, true));
+        println!("{}", frame_info.origin);
+
+        frames.push(Frame::from_frame_data(handle, frame_info));
+    }
+
+    Ok((
+        Animation::from_info(frames, animation_file_data.info),
+        animation_file_data.editor_view,
+    ))
+}
+
+/// Name of the sidecar file of a PNG sequence, holding everything but the images.
+const SEQUENCE_INFO_FILE: &str = "frames.json";
+
+fn sequence_frame_file(index: usize) -> String {
+    format!("frame_{index:03}.png")
+}
+
+/// Writes every frame of `animation` untrimmed as `frame_NNN.png` into `dir`, along with a
+/// [`SEQUENCE_INFO_FILE`] holding the same [`Info`] as an `.anim` file. Its cell size and
+/// columns are 0, as there is no spritesheet.
+fn write_sequence(
+    dir: impl AsRef<Path>,
+    animation: &Animation,
+    assets: &Assets<Image>,
+) -> Result<(), Box<dyn Error>> {
+    let dir = dir.as_ref();
+    for (index, frame) in animation.timeline.frames.iter().enumerate() {
+        let image = assets
+            .get(&frame.image)
+            .ok_or("a frame's image is not loaded")?;
+        image
+            .clone()
+            .try_into_dynamic()?
+            .save_with_format(dir.join(sequence_frame_file(index)), ImageFormat::Png)?;
+    }
+
+    let info = Info {
+        cell_width: 0,
+        cell_height: 0,
+        columns: 0,
+        frame_count: animation.timeline.frames.len(),
+        frame_data: animation
+            .timeline
+            .frames
+            .iter()
+            .map(|frame| frame.to_frame_data().in_y_axis(animation.y_axis))
+            .collect(),
+        hitboxes: animation.hitboxes.clone(),
+        hitbox_order: animation.hitbox_order.clone(),
+        anchor_relative_hitboxes: animation.anchor_relative_hitboxes,
+        scale_hitboxes: animation.scale_hitboxes,
+        active_windows: animation.active_windows(),
+        tags: animation.tags.clone(),
+        metadata: animation.metadata.clone(),
+        fps: animation.fps,
+        y_axis: animation.y_axis,
+    };
+    serde_json::to_writer_pretty(std::fs::File::create(dir.join(SEQUENCE_INFO_FILE))?, &info)?;
+    Ok(())
+}
+
+/// The files [`write_boxes_by_kind`] splits the gameplay data into, and the kind of box each
+/// holds.
+const BOX_KIND_FILES: [(&str, BoxKind); 3] = [
+    ("hitboxes.json", BoxKind::Hit),
+    ("hurtboxes.json", BoxKind::Hurt),
+    ("collision.json", BoxKind::Collision),
+];
+
+/// The gameplay data of one kind of box, for engines where different systems own hit, hurt
+/// and collision data.
+#[derive(Serialize)]
+struct BoxKindData<'a> {
+    hitboxes: HashMap<usize, &'a Hitbox>,
+    hitbox_order: Vec<usize>,
+    /// Per frame, the delay and the positions of this kind's boxes on it.
+    frames: Vec<BoxKindFrame>,
+    active_windows: HashMap<usize, Vec<[usize; 2]>>,
+    anchor_relative_hitboxes: bool,
+    scale_hitboxes: bool,
+    fps: u32,
+    y_axis: YAxis,
+}
+
+#[derive(Serialize)]
+struct BoxKindFrame {
+    delay: usize,
+    repeat: u32,
+    hitboxes: HashMap<usize, HitboxPos>,
+}
+
+/// Writes the hitbox data of `animation` into `dir` as one file per box kind, named as in
+/// [`BOX_KIND_FILES`].
+fn write_boxes_by_kind(dir: impl AsRef<Path>, animation: &Animation) -> Result<(), Box<dyn Error>> {
+    let mut active_windows = animation.active_windows();
+    for (file_name, kind) in BOX_KIND_FILES {
+        let is_kind = |id: &usize| animation.hitboxes[id].kind == kind;
+        let data = BoxKindData {
+            hitboxes: animation
+                .hitboxes
+                .iter()
+                .filter(|(id, _)| is_kind(id))
+                .map(|(&id, hitbox)| (id, hitbox))
+                .collect(),
+            hitbox_order: animation
+                .hitbox_order
+                .iter()
+                .copied()
+                .filter(is_kind)
+                .collect(),
+            frames: animation
+                .timeline
+                .frames
+                .iter()
+                .map(|frame| BoxKindFrame {
+                    delay: frame.delay,
+                    repeat: frame.repeat,
+                    hitboxes: frame
+                        .hitboxes
+                        .iter()
+                        .filter(|(id, _)| is_kind(id))
+                        .map(|(&id, hp)| {
+                            let mut hp = hp.clone();
+                            if animation.y_axis == YAxis::Down {
+                                hp.pos.y = -hp.pos.y;
+                            }
+                            (id, hp)
+                        })
+                        .collect(),
+                })
+                .collect(),
+            active_windows: active_windows.drain_filter(|id, _| is_kind(id)).collect(),
+            anchor_relative_hitboxes: animation.anchor_relative_hitboxes,
+            scale_hitboxes: animation.scale_hitboxes,
+            fps: animation.fps,
+            y_axis: animation.y_axis,
+        };
+        serde_json::to_writer_pretty(std::fs::File::create(dir.as_ref().join(file_name))?, &data)?;
+    }
+    Ok(())
+}
+
+/// Reads a PNG sequence written by [`write_sequence`] back into an animation. Frames whose
+/// image is missing or unreadable, e.g. after files were moved, get a placeholder instead of
+/// failing the whole import; their indices are returned alongside.
+fn load_sequence(
+    dir: impl AsRef<Path>,
+    assets: &mut Assets<Image>,
+) -> Result<(Animation, Vec<usize>), Box<dyn Error>> {
+    let dir = dir.as_ref();
+    let info_path = dir.join(SEQUENCE_INFO_FILE);
+    let info = serde_json::from_reader::<_, Info>(
+        std::fs::File::open(&info_path)
+            .map_err(|err| format!("could not open {}: {err}", info_path.display()))?,
+    )?
+    .into_y_up();
+
+    let mut frames = vec![];
+    let mut missing = vec![];
+    for (index, frame_data) in info.frame_data.iter().enumerate() {
+        let file = match &frame_data.image {
+            Some(file) => dir.join(file),
+            None => dir.join(sequence_frame_file(index)),
+        };
+        let image = match image::open(file) {
+            Ok(image) => image,
+            Err(_) => {
+                missing.push(index);
+                placeholder_image()
+            }
+        };
+        let handle = assets.add(Image::from_dynamic(image, true));
+        frames.push(Frame::from_frame_data(handle, frame_data));
+    }
+
+    Ok((Animation::from_info(frames, info), missing))
+}
+
+/// The images of an imported PNG sequence that were missing and replaced with placeholders.
+struct MissingImages {
+    /// The sequence folder.
+    dir: PathBuf,
+    images: Vec<MissingImage>,
+}
+
+struct MissingImage {
+    /// Index of the frame in the sequence's [`SEQUENCE_INFO_FILE`].
+    index: usize,
+    /// Locating the image replaces the placeholder's pixels, so every frame and undo snapshot
+    /// showing it picks the image up.
+    placeholder: Handle<Image>,
+    /// Where the user found the image. It is written into the sequence on the next save.
+    located: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ImageHandle {
+    path: String,
+    #[serde(skip)]
+    image: Handle<Image>,
+}
+
+/// Direction of [`EditorState::bake_motion`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MotionBake {
+    RootMotionToOffset,
+    OffsetToRootMotion,
+}
+
+impl MotionBake {
+    fn label(self) -> &'static str {
+        match self {
+            MotionBake::RootMotionToOffset => "root motion into offset",
+            MotionBake::OffsetToRootMotion => "offset into root motion",
+        }
+    }
+}
+
+/// Axis through the anchor that [`EditorState::mirror_hitboxes`] mirrors across.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MirrorAxis {
+    Vertical,
+    Horizontal,
+}
+
+impl MirrorAxis {
+    fn label(self) -> &'static str {
+        match self {
+            MirrorAxis::Vertical => "left to right",
+            MirrorAxis::Horizontal => "top to bottom",
+        }
+    }
+}
+
+/// A hitbox about to be created, whose position and size can still be typed in.
+#[derive(Clone, Copy)]
+struct HitboxDraft {
+    frame_index: usize,
+    pos: Vec2,
+    size: Vec2,
+    /// Where to show the popup, from the top left of the window; centered if `None`.
+    screen_pos: Option<Vec2>,
+}
+
+/// A likely authoring mistake between two boxes on a frame, found by
+/// [`EditorState::check_hitboxes`].
+#[derive(Clone, Copy)]
+struct HitboxIssue {
+    frame_index: usize,
+    ids: [usize; 2],
+    kind: HitboxIssueKind,
+}
+
+#[derive(Clone, Copy)]
+enum HitboxIssueKind {
+    /// Both boxes have the same position and size.
+    Duplicate,
+    /// The first box is a hurtbox lying entirely inside the second, a collision box.
+    HurtboxInsideCollisionBox,
+}
+
+/// The menu opened by right-clicking the canvas.
+#[derive(Clone, Copy)]
+struct CanvasMenu {
+    frame_index: usize,
+    /// The hitbox that was clicked, or `None` for empty canvas.
+    hitbox: Option<usize>,
+    /// The clicked point in hitbox space.
+    pos: Vec2,
+    /// Where the click was, from the top left of the window.
+    screen_pos: Vec2,
+}
+
+/// Integer nearest-neighbor scale applied to imported or existing frames.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ImageScale {
+    Half,
+    One,
+    Double,
+    Triple,
+}
+
+impl ImageScale {
+    const ALL: [ImageScale; 4] = [
+        ImageScale::Half,
+        ImageScale::One,
+        ImageScale::Double,
+        ImageScale::Triple,
+    ];
+
+    fn factor(self) -> f32 {
+        match self {
+            ImageScale::Half => 0.5,
+            ImageScale::One => 1.0,
+            ImageScale::Double => 2.0,
+            ImageScale::Triple => 3.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ImageScale::Half => "½×",
+            ImageScale::One => "1×",
+            ImageScale::Double => "2×",
+            ImageScale::Triple => "3×",
+        }
+    }
+
+    fn apply(self, image: DynamicImage) -> DynamicImage {
+        if self == ImageScale::One {
+            return image;
+        }
+        let factor = self.factor();
+        let width = ((image.width() as f32 * factor) as u32).max(1);
+        let height = ((image.height() as f32 * factor) as u32).max(1);
+        image.resize_exact(width, height, FilterType::Nearest)
+    }
+}
+
+/// Maps between world positions and a frame's hitbox positions: hitboxes are placed at
+/// `anchor + origin + pos`, mirrored around `anchor` on flipped axes.
+#[derive(Clone, Copy)]
+struct HitboxSpace {
+    anchor: Vec2,
+    origin: Vec2,
+    flip: Vec2,
+    scale: Vec2,
+}
+
+impl HitboxSpace {
+    fn from_world(&self, world_pos: Vec2) -> Vec2 {
+        (world_pos - self.anchor) * self.flip / self.scale - self.origin
+    }
+
+    fn to_world(&self, pos: Vec2) -> Vec2 {
+        self.anchor + (self.origin + pos) * self.flip * self.scale
+    }
+
+    fn world_rect(&self, hp: &HitboxPos) -> Rect {
+        let rect = hp.rect();
+        Rect::from_corners(self.to_world(rect.min), self.to_world(rect.max))
+    }
+}
+
+/// A static guide image shown behind the canvas, for rotoscoping or matching a concept. It
+/// isn't part of the animation, so it is never saved, exported or picked by tools.
+struct Lightbox {
+    image: Option<Handle<Image>>,
+    /// World position of the image's center.
+    position: Vec2,
+    scale: f32,
+    opacity: f32,
+}
+
+/// Display settings hidden by preview mode.
+struct EditLayout {
+    show_hitboxes: bool,
+    show_grid: bool,
+    show_trim_preview: bool,
+    show_saved_cell: bool,
+    show_trim_comparison: bool,
+    animation_running: bool,
+}
+
+#[derive(Clone)]
+struct ExportSettings {
+    max_texture_size: u32,
+    multi_page_atlas: bool,
+    /// Store each frame trimmed to its content with its own rect, instead of padding every
+    /// frame to a common grid cell.
+    tight_packing: bool,
+    /// Pixels with an alpha at or below this are trimmed away like fully transparent ones,
+    /// so faint fringe pixels don't grow the cell.
+    alpha_threshold: u8,
+    /// Save runs of identical consecutive frames as one frame with their combined duration;
+    /// see [`Animation::collapse_held_frames`].
+    collapse_held_frames: bool,
+}
+
+#[derive(Resource)]
+struct EditorState {
+    current_animation: Animation,
+    current_basepath: Option<String>,
+    current_frame: usize,
+    action_list: Vec<Action>,
+    undo_depth: usize,
+    /// How many actions of `action_list` were applied when the file was last saved, or `None`
+    /// if undo and redo can no longer get back to the saved state.
+    edits_at_save: Option<usize>,
+    /// Whether the last action came from [`EditorState::do_action_merging`] and may still
+    /// absorb the next one.
+    merge_open: bool,
+    drag_starting_pos: Option<Vec2>,
+    /// Position of the hitbox being resized from its center, restored or recorded along with
+    /// its size.
+    resize_starting_pos: Option<Vec2>,
+    /// The dragged hitbox position or size before snapping.
+    unsnapped_drag: Option<Vec2>,
+    selected_tool: Tool,
+    currently_selected_box: Option<usize>,
+    selected_boxes: HashSet<usize>,
+    marquee: Option<(Vec2, Vec2)>,
+    has_saved: bool,
+    action_after_save: Option<Box<dyn FnOnce(&mut EditorState) + Send + Sync>>,
+    exit_now: bool,
+    command_queue: Vec<EditorCommand>,
+    animation_running: bool,
+    frames_since_last_frame: usize,
+    interpolate_playback: bool,
+    interaction_lock: InteractionLock,
+    always_show_root_motion: bool,
+    /// Whether hitboxes move with the sprite while root motion is shown. When off they stay
+    /// at their local positions, around the unmoved anchor.
+    hitboxes_follow_root_motion: bool,
+    show_hitboxes: bool,
+    /// Whether shift-dragging resizes hitboxes symmetrically around their center.
+    resize_from_center: bool,
+    /// Whether dragged hitbox edges snap to the edges of the other hitboxes.
+    snap_hitboxes: bool,
+    grid_at_anchor: bool,
+    show_grid: bool,
+    /// Half-size of the root-motion marker in screen pixels.
+    marker_size: f32,
+    /// Size of the last created or resized hitbox, used as the default for new ones.
+    last_hitbox_size: Vec2,
+    /// Whether creating a hitbox first asks for its exact position and size.
+    ask_hitbox_size: bool,
+    /// The hitbox waiting for its position and size to be confirmed.
+    hitbox_draft: Option<HitboxDraft>,
+    canvas_menu: Option<CanvasMenu>,
+    /// The edit layout to restore when leaving preview mode; `Some` while previewing.
+    preview_mode: Option<EditLayout>,
+    export_settings: ExportSettings,
+    oversized_atlas: Option<(u32, u32)>,
+    /// Inclusive range of frames selected in the timeline with shift-click.
+    selected_frames: Option<(usize, usize)>,
+    import_scale: ImageScale,
+    /// Color artists paint a single pixel with to mark a frame's pivot.
+    pivot_marker_color: [u8; 3],
+    show_trim_preview: bool,
+    show_saved_cell: bool,
+    /// Whether the current frame is shown as drawn and as saved, side by side.
+    show_trim_comparison: bool,
+    /// Whether the frame rate and frame time of the editor itself are shown.
+    show_performance: bool,
+    /// View-only tint and alpha multiplied into the displayed sprite.
+    sprite_tint: [f32; 4],
+    workspace_presets: Vec<WorkspacePreset>,
+    /// Started with `--safe`, to recover from a file that breaks startup. The editor's own
+    /// files, like the workspace presets, are then neither read nor overwritten.
+    safe_mode: bool,
+    trim_preview: Option<TrimPreview>,
+    /// Frame drawn translucently over the current one, for comparing e.g. the ends of a loop.
+    reference_frame: Option<usize>,
+    /// Missing images of the imported PNG sequence, while there are any to locate or write.
+    missing_images: Option<MissingImages>,
+    lightbox: Lightbox,
+    /// The pixel under the cursor while the eyedropper is selected.
+    sampled_pixel: Option<SampledPixel>,
+    /// The camera's position and zoom, kept up to date by [`sync_camera`] for saving.
+    camera_view: (Vec2, f32),
+    /// A camera position and zoom for [`sync_camera`] to move to, e.g. after opening a file.
+    pending_camera_view: Option<(Vec2, f32)>,
+    /// Set to have [`capture_canvas`] draw the canvas and ask where to save it.
+    canvas_capture_requested: bool,
+    /// Set to have [`sync_camera`] fit the view to the current frame's sprite and hitboxes.
+    fit_view_requested: bool,
+    #[cfg(feature = "live-link")]
+    live_link_enabled: bool,
+    /// Bumped whenever the animation is replaced or an action changes it.
+    #[cfg(feature = "live-link")]
+    revision: u64,
+}
+
+/// A pixel of the current frame's image, as read by the eyedropper.
+#[derive(Clone, Copy)]
+struct SampledPixel {
+    pos: UVec2,
+    rgba: [u8; 4],
+}
+
+/// How the current frame will be trimmed and padded into its cell on the next save.
+#[derive(Clone, Copy)]
+struct TrimPreview {
+    /// Where the anchor will be within the cell.
+    offset: Vec2,
+    /// Transparent padding around the trimmed content, as left, top, right and bottom.
+    padding: [u32; 4],
+    /// The content kept by the trim within the source image, as left, top, width and height.
+    content: [u32; 4],
+}
+
+impl EditorState {
+    fn new(safe_mode: bool) -> Self {
+        Self {
+            current_animation: Animation::new(),
+            current_basepath: None,
+            current_frame: 0,
+            action_list: vec![],
+            undo_depth: 0,
+            edits_at_save: Some(0),
+            merge_open: false,
+            drag_starting_pos: None,
+            resize_starting_pos: None,
+            unsnapped_drag: None,
+            selected_tool: Tool::Select,
+            currently_selected_box: None,
+            selected_boxes: HashSet::new(),
+            marquee: None,
+            has_saved: true,
+            action_after_save: None,
+            exit_now: false,
+            command_queue: vec![],
+            animation_running: false,
+            frames_since_last_frame: 0,
+            interpolate_playback: false,
+            interaction_lock: InteractionLock::None,
+            always_show_root_motion: false,
+            hitboxes_follow_root_motion: true,
+            show_hitboxes: true,
+            resize_from_center: false,
+            snap_hitboxes: true,
+            grid_at_anchor: false,
+            show_grid: true,
+            marker_size: 10.0,
+            last_hitbox_size: Vec2::new(8.0, 8.0),
+            ask_hitbox_size: false,
+            hitbox_draft: None,
+            canvas_menu: None,
+            preview_mode: None,
+            export_settings: ExportSettings {
+                max_texture_size: 8192,
+                multi_page_atlas: false,
+                tight_packing: false,
+                alpha_threshold: 0,
+                collapse_held_frames: false,
+            },
+            oversized_atlas: None,
+            selected_frames: None,
+            import_scale: ImageScale::One,
+            pivot_marker_color: [255, 0, 255],
+            show_trim_preview: false,
+            show_saved_cell: false,
+            show_trim_comparison: false,
+            show_performance: false,
+            sprite_tint: [1.0; 4],
+            workspace_presets: vec![],
+            safe_mode,
+            trim_preview: None,
+            reference_frame: None,
+            missing_images: None,
+            lightbox: Lightbox {
+                image: None,
+                position: Vec2::ZERO,
+                scale: 1.0,
+                opacity: 0.5,
+            },
+            sampled_pixel: None,
+            camera_view: (Vec2::ZERO, DEFAULT_ZOOM),
+            pending_camera_view: None,
+            canvas_capture_requested: false,
+            fit_view_requested: false,
+            #[cfg(feature = "live-link")]
+            live_link_enabled: false,
+            #[cfg(feature = "live-link")]
+            revision: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.current_animation = Animation::new();
+        self.current_frame = 0;
+        self.reference_frame = None;
+        self.missing_images = None;
+        self.has_saved = true;
+        self.action_list = vec![];
+        self.undo_depth = 0;
+        self.edits_at_save = Some(0);
+        self.action_after_save = None;
+        self.current_basepath = None;
+        self.currently_selected_box = None;
+        self.selected_boxes.clear();
+        self.selected_frames = None;
+        self.drag_starting_pos = None;
+        self.bump_revision();
+    }
+
+    fn confirm_if_unsaved(
+        &mut self,
+        ui_state: &mut UiState,
+        action: impl FnOnce(&mut Self) + Send + Sync + 'static,
+        unlock_on_non_cancel: bool,
+    ) {
+        if self.has_saved {
+            action(self);
+        } else {
+            self.animation_running = false;
+            self.frames_since_last_frame = 0;
+            self.interaction_lock.lock_modal();
+            ui_state.show_save_menu = true;
+            ui_state.save_menu_unlock_on_non_cancel = unlock_on_non_cancel;
+            self.action_after_save = Some(Box::new(action));
+        }
+    }
+
+    /// Writes the frames in `range` as a standalone animation, keeping only the hitboxes
+    /// they reference. Hitbox ids are remapped to `0..n` in their original order.
+    fn export_frames(
+        &mut self,
+        range: RangeInclusive<usize>,
+        path: impl AsRef<Path>,
+        assets: &Assets<Image>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut frames = self.current_animation.timeline.frames[range.clone()].to_vec();
+
+        let mut ids = frames
+            .iter()
+            .flat_map(|frame| frame.hitboxes.keys().copied())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let id_map = ids
+            .iter()
+            .enumerate()
+            .map(|(new, &old)| (old, new))
+            .collect::<HashMap<_, _>>();
+
+        for frame in &mut frames {
+            frame.hitboxes = Arc::new(
+                frame
+                    .hitboxes
+                    .iter()
+                    .map(|(id, hitbox_pos)| {
+                        let id = id_map[id];
+                        (
+                            id,
+                            HitboxPos {
+                                id,
+                                ..hitbox_pos.clone()
+                            },
+                        )
+                    })
+                    .collect(),
+            );
+        }
+
+        let hitboxes = id_map
+            .iter()
+            .filter_map(|(old, &new)| {
+                let hitbox = self.current_animation.hitboxes.get(old)?;
+                Some((
+                    new,
+                    Hitbox {
+                        id: new,
+                        ..hitbox.clone()
+                    },
+                ))
+            })
+            .collect();
+
+        let tags = self
+            .current_animation
+            .tags
+            .iter()
+            .filter(|tag| tag.start <= *range.end() && tag.end >= *range.start())
+            .map(|tag| FrameTag {
+                start: tag.start.max(*range.start()) - range.start(),
+                end: tag.end.min(*range.end()) - range.start(),
+                ..tag.clone()
+            })
+            .collect();
+
+        let hitbox_order = self
+            .current_animation
+            .hitbox_order
+            .iter()
+            .filter_map(|id| id_map.get(id).copied())
+            .collect();
+
+        let animation = Animation {
+            timeline: Timeline { frames },
+            hitboxes,
+            hitbox_order,
+            anchor_relative_hitboxes: self.current_animation.anchor_relative_hitboxes,
+            scale_hitboxes: self.current_animation.scale_hitboxes,
+            tags,
+            metadata: self.current_animation.metadata.clone(),
+            fps: self.current_animation.fps,
+            y_axis: self.current_animation.y_axis,
+        };
+
+        if let Some(size) = write_animation(path, &animation, assets, &self.export_settings, None)?
+        {
+            self.oversized_atlas = Some(size);
+        }
+
+        Ok(())
+    }
+
+    /// Rescales every frame's image, offsets and hitboxes as a single undoable action. Nothing is
+    /// scaled if any frame's image can't be read.
+    fn scale_animation(
+        &mut self,
+        scale: ImageScale,
+        assets: &mut Assets<Image>,
+    ) -> Result<(), String> {
+        if scale == ImageScale::One {
+            return Ok(());
+        }
+        let frames = &self.current_animation.timeline.frames;
+        let mut images = Vec::with_capacity(frames.len());
+        for (index, frame) in frames.iter().enumerate() {
+            let image = assets
+                .get(&frame.image)
+                .ok_or_else(|| format!("the image of frame {} is not loaded", index + 1))?
+                .clone()
+                .try_into_dynamic()
+                .map_err(|err| format!("frame {}: {err}", index + 1))?;
+            images.push(image);
+        }
+        let mut actions = vec![];
+        for ((index, frame), image) in frames.iter().enumerate().zip(images) {
+            let handle = assets.add(Image::from_dynamic(scale.apply(image), true));
+            actions.push(Action::ReplaceFrame {
+                index,
+                from: Box::new(frame.clone()),
+                to: Box::new(frame.scaled(handle, scale.factor())),
+            });
+        }
+        self.do_action(Action::Group(actions));
+        Ok(())
+    }
+
+    /// Moves each frame's anchor to the first pixel of `pivot_marker_color` and clears that
+    /// pixel, as one undoable action. Returns the frames without a marker.
+    fn apply_pivot_markers(&mut self, assets: &mut Assets<Image>) -> Vec<usize> {
+        let [r, g, b] = self.pivot_marker_color;
+        let mut actions = vec![];
+        let mut missing = vec![];
+        for (index, frame) in self.current_animation.timeline.frames.iter().enumerate() {
+            let Some(image) = assets
+                .get(&frame.image)
+                .and_then(|image| image.clone().try_into_dynamic().ok())
+            else {
+                missing.push(index);
+                continue;
+            };
+            let mut image = image.into_rgba8();
+            let marker = image
+                .enumerate_pixels()
+                .find(|(_, _, pixel)| pixel[3] > 0 && pixel.0[..3] == [r, g, b])
+                .map(|(x, y, _)| (x, y));
+            let Some((x, y)) = marker else {
+                missing.push(index);
+                continue;
+            };
+            image.put_pixel(x, y, image::Rgba([0, 0, 0, 0]));
+            let handle = assets.add(Image::from_dynamic(DynamicImage::ImageRgba8(image), true));
+            actions.push(Action::ReplaceFrame {
+                index,
+                from: Box::new(frame.clone()),
+                to: Box::new(Frame {
+                    image: handle,
+                    offset: Vec2::new(x as f32, y as f32),
+                    ..frame.clone()
+                }),
+            });
+        }
+        self.do_action(Action::Group(actions));
+        missing
+    }
+
+    /// Writes the image of frame `index` to `path` as a PNG, optionally trimmed to its
+    /// non-transparent pixels.
+    fn export_frame_png(
+        &self,
+        index: usize,
+        trimmed: bool,
+        path: impl AsRef<Path>,
+        assets: &Assets<Image>,
+    ) -> Result<(), Box<dyn Error>> {
+        let image = assets
+            .get(&self.frame(index).image)
+            .ok_or("the frame's image is not loaded")?;
+        let mut image = image.clone().try_into_dynamic()?;
+        if trimmed {
+            let (left, top, width, height) =
+                content_bounds(&image, self.export_settings.alpha_threshold);
+            image = image.crop_imm(left, top, width, height);
+        }
+        image.save_with_format(path, ImageFormat::Png)?;
+        Ok(())
+    }
+
+    /// A save dialog for `.anim` files, suggesting a file name from the animation's name.
+    fn save_dialog(&self) -> rfd::AsyncFileDialog {
+        let dialog = FileKind::Anim.dialog();
+        let name = &self.current_animation.metadata.name;
+        if name.is_empty() {
+            dialog
+        } else {
+            dialog.set_file_name(&format!("{name}.anim"))
+        }
+    }
+
+    fn save(
+        &mut self,
+        pending_file_dialog: &mut PendingFileDialog,
+        assets: &Assets<Image>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = self.current_basepath.clone() {
+            self.save_to(path, assets)?;
+        } else {
+            let future = self.save_dialog().save_file();
+            self.interaction_lock.lock_all();
+            self.animation_running = false;
+            self.frames_since_last_frame = 0;
+            pending_file_dialog.action = Some(FileAction::Save(Box::pin(future)));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the animation to `path`. On failure the animation stays unsaved and any
+    /// pending `action_after_save` is dropped, so the user can retry.
+    fn save_to(
+        &mut self,
+        path: impl AsRef<Path>,
+        assets: &Assets<Image>,
+    ) -> Result<(), Box<dyn Error>> {
+        match write_animation(
+            &path,
+            &self.current_animation,
+            assets,
+            &self.export_settings,
+            Some(self.editor_view()),
+        ) {
+            Ok(oversized) => {
+                if let Some(size) = oversized {
+                    self.oversized_atlas = Some(size);
+                }
+            }
+            Err(err) => {
+                self.action_after_save = None;
+                return Err(err);
+            }
+        }
+        if let Err(err) = self.write_image_locations() {
+            self.action_after_save = None;
+            return Err(format!("could not record the located images: {err}").into());
+        }
+
+        self.has_saved = true;
+        self.edits_at_save = Some(self.action_list.len() - self.undo_depth);
+
+        if let Some(action) = self.action_after_save.take() {
+            action(self);
+        }
+
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        path: impl AsRef<Path>,
+        assets: &mut Assets<Image>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (animation, editor_view) = load(&path, assets)?;
+        self.replace_animation(animation, Some(path.as_ref().to_string_lossy().to_string()));
+        if let Some(view) = editor_view {
+            self.apply_editor_view(view);
+        }
+        Ok(())
+    }
+
+    fn editor_view(&self) -> EditorView {
+        let show_hitboxes = self
+            .preview_mode
+            .as_ref()
+            .map_or(self.show_hitboxes, |layout| layout.show_hitboxes);
+        let (camera_position, zoom) = self.camera_view;
+        EditorView {
+            selected_tool: self.selected_tool,
+            show_hitboxes,
+            always_show_root_motion: self.always_show_root_motion,
+            hitboxes_follow_root_motion: self.hitboxes_follow_root_motion,
+            camera_position,
+            zoom,
+        }
+    }
+
+    fn apply_editor_view(&mut self, view: EditorView) {
+        self.selected_tool = view.selected_tool;
+        match &mut self.preview_mode {
+            Some(layout) => layout.show_hitboxes = view.show_hitboxes,
+            None => self.show_hitboxes = view.show_hitboxes,
+        }
+        self.always_show_root_motion = view.always_show_root_motion;
+        self.hitboxes_follow_root_motion = view.hitboxes_follow_root_motion;
+        if view.zoom > 0.0 {
+            self.pending_camera_view = Some((view.camera_position, view.zoom));
+        }
+    }
+
+    /// Saves the current view settings as a preset named `name`, replacing any preset with
+    /// that name.
+    fn save_workspace_preset(&mut self, name: String) -> Result<(), Box<dyn Error>> {
+        if self.safe_mode {
+            return Err("workspace presets can't be changed in safe mode".into());
+        }
+        let (show_grid, show_trim_preview, show_saved_cell) = match &self.preview_mode {
+            Some(layout) => (
+                layout.show_grid,
+                layout.show_trim_preview,
+                layout.show_saved_cell,
+            ),
+            None => (self.show_grid, self.show_trim_preview, self.show_saved_cell),
+        };
+        let preset = WorkspacePreset {
+            name,
+            view: self.editor_view(),
+            show_grid,
+            grid_at_anchor: self.grid_at_anchor,
+            show_trim_preview,
+            show_saved_cell,
+            marker_size: self.marker_size,
+            sprite_tint: self.sprite_tint,
+        };
+        match self
+            .workspace_presets
+            .iter_mut()
+            .find(|existing| existing.name == preset.name)
+        {
+            Some(existing) => *existing = preset,
+            None => self.workspace_presets.push(preset),
+        }
+        write_workspace_presets(&self.workspace_presets)
+    }
+
+    fn delete_workspace_preset(&mut self, index: usize) -> Result<(), Box<dyn Error>> {
+        if self.safe_mode {
+            return Err("workspace presets can't be changed in safe mode".into());
+        }
+        self.workspace_presets.remove(index);
+        write_workspace_presets(&self.workspace_presets)
+    }
+
+    fn apply_workspace_preset(&mut self, index: usize) {
+        let preset = self.workspace_presets[index].clone();
+        self.apply_editor_view(preset.view);
+        match &mut self.preview_mode {
+            Some(layout) => {
+                layout.show_grid = preset.show_grid;
+                layout.show_trim_preview = preset.show_trim_preview;
+                layout.show_saved_cell = preset.show_saved_cell;
+            }
+            None => {
+                self.show_grid = preset.show_grid;
+                self.show_trim_preview = preset.show_trim_preview;
+                self.show_saved_cell = preset.show_saved_cell;
+            }
+        }
+        self.grid_at_anchor = preset.grid_at_anchor;
+        self.marker_size = preset.marker_size;
+        self.sprite_tint = preset.sprite_tint;
+    }
+
+    /// Opens a PNG sequence folder. It has no `.anim` file yet, so saving asks for one.
+    fn import_sequence(
+        &mut self,
+        dir: impl AsRef<Path>,
+        assets: &mut Assets<Image>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let (animation, missing) = load_sequence(&dir, assets)?;
+        self.replace_animation(animation, None);
+        if !missing.is_empty() {
+            let frames = &self.current_animation.timeline.frames;
+            self.missing_images = Some(MissingImages {
+                dir: dir.as_ref().to_owned(),
+                images: missing
+                    .iter()
+                    .map(|&index| MissingImage {
+                        index,
+                        placeholder: frames[index].image.clone(),
+                        located: None,
+                    })
+                    .collect(),
+            });
+        }
+        Ok(missing)
+    }
+
+    /// Shows the image at `path` in place of the placeholder for the missing sequence image
+    /// `index`, and keeps the location to write into the sequence on the next save.
+    fn locate_image(
+        &mut self,
+        index: usize,
+        path: &Path,
+        assets: &mut Assets<Image>,
+    ) -> Result<(), Box<dyn Error>> {
+        let missing = self
+            .missing_images
+            .as_mut()
+            .and_then(|missing| missing.images.iter_mut().find(|image| image.index == index))
+            .ok_or("the image is no longer missing")?;
+        let image = image::open(path)?;
+        let placeholder = assets
+            .get_mut(&missing.placeholder)
+            .ok_or("the placeholder is no longer loaded")?;
+        *placeholder = Image::from_dynamic(image, true);
+        missing.located = Some(path.to_owned());
+        self.edit_outside_history();
+        self.bump_revision();
+        Ok(())
+    }
+
+    /// Writes the located images into the imported sequence's [`SEQUENCE_INFO_FILE`], so
+    /// that importing it again finds them.
+    fn write_image_locations(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(missing) = &mut self.missing_images else {
+            return Ok(());
+        };
+        if missing.images.iter().all(|image| image.located.is_none()) {
+            return Ok(());
+        }
+
+        let info_path = missing.dir.join(SEQUENCE_INFO_FILE);
+        let mut info: Info = serde_json::from_reader(std::fs::File::open(&info_path)?)?;
+        for image in &missing.images {
+            if let Some(path) = &image.located
+                && let Some(frame_data) = info.frame_data.get_mut(image.index)
+            {
+                let path = path.strip_prefix(&missing.dir).unwrap_or(path);
+                frame_data.image = Some(path.to_string_lossy().into_owned());
+            }
+        }
+        serde_json::to_writer_pretty(std::fs::File::create(&info_path)?, &info)?;
+
+        missing.images.retain(|image| image.located.is_none());
+        if missing.images.is_empty() {
+            self.missing_images = None;
+        }
+        Ok(())
+    }
 
+    fn replace_animation(&mut self, animation: Animation, basepath: Option<String>) {
+        self.current_animation = animation;
+        self.current_frame = 0;
+        self.reference_frame = None;
+        self.missing_images = None;
+        self.current_basepath = basepath;
+        self.action_list = vec![];
+        self.undo_depth = 0;
+        self.edits_at_save = Some(0);
         self.has_saved = true;
+        self.bump_revision();
+    }
+
+    fn do_action(&mut self, action: Action) {
+        if action.warrants_action() {
+            self.merge_open = false;
+            if self
+                .edits_at_save
+                .is_some_and(|n| n > self.action_list.len() - self.undo_depth)
+            {
+                self.edits_at_save = None;
+            }
+            for _ in 0..self.undo_depth {
+                self.action_list.pop().unwrap();
+            }
+            self.undo_depth = 0;
+            action.apply(self);
+            if let Some(size) = action.hitbox_size() {
+                self.last_hitbox_size = size;
+            }
+            self.action_list.push(action);
+            self.clamp_current_frame();
+            self.bump_revision();
+
+            self.has_saved = false;
+        }
+    }
+
+    /// Like [`EditorState::do_action`], but merges `action` into the previous one if that
+    /// also came from here and edits the same value, so that dragging a value is a single
+    /// undo step. Call [`EditorState::end_action_merge`] when a new edit starts.
+    fn do_action_merging(&mut self, action: Action) {
+        let merged = if self.merge_open
+            && self.undo_depth == 0
+            && self.edits_at_save != Some(self.action_list.len())
+        {
+            self.action_list
+                .last()
+                .and_then(|last| last.try_merge(&action))
+        } else {
+            None
+        };
+
+        let Some(merged) = merged else {
+            self.do_action(action);
+            self.merge_open = true;
+            return;
+        };
+        action.apply(self);
+        if let Some(size) = action.hitbox_size() {
+            self.last_hitbox_size = size;
+        }
+        self.action_list.pop();
+        // Dragging back to where the edit started leaves nothing to undo.
+        if merged.warrants_action() {
+            self.action_list.push(merged);
+        } else {
+            self.merge_open = false;
+        }
+        self.clamp_current_frame();
+        self.bump_revision();
+
+        self.has_saved = self.at_save_point();
+    }
+
+    /// Whether undo and redo have brought the animation back to how it was last saved.
+    fn at_save_point(&self) -> bool {
+        self.edits_at_save == Some(self.action_list.len() - self.undo_depth)
+    }
+
+    /// Marks the animation unsaved after a change that bypasses the undo history, and sends it
+    /// to live link clients.
+    fn edit_outside_history(&mut self) {
+        self.has_saved = false;
+        self.edits_at_save = None;
+        self.bump_revision();
+    }
+
+    fn end_action_merge(&mut self) {
+        self.merge_open = false;
+    }
+
+    fn undo(&mut self) {
+        self.merge_open = false;
+        if self.undo_depth >= self.action_list.len() {
+            return;
+        }
+        self.undo_depth += 1;
+        let action = self.action_list[self.action_list.len() - self.undo_depth].clone();
+        action.reverse(self);
+        self.clamp_current_frame();
+        self.bump_revision();
+
+        self.has_saved = self.at_save_point();
+    }
+
+    fn redo(&mut self) {
+        if self.undo_depth == 0 {
+            return;
+        }
+
+        let action = self.action_list[self.action_list.len() - self.undo_depth].clone();
+        action.apply(self);
+        self.undo_depth -= 1;
+        self.clamp_current_frame();
+        self.bump_revision();
+
+        self.has_saved = self.at_save_point();
+    }
+
+    fn bump_revision(&mut self) {
+        #[cfg(feature = "live-link")]
+        {
+            self.revision += 1;
+        }
+    }
+
+    fn set_playback(&mut self, running: bool) {
+        self.animation_running = running;
+        self.frames_since_last_frame = 0;
+        if running {
+            self.interaction_lock.lock_playback();
+        } else {
+            self.interaction_lock.release();
+        }
+    }
+
+    /// Switches between the edit layout and a clean, playing preview of just the sprite.
+    fn toggle_preview_mode(&mut self) {
+        if let Some(layout) = self.preview_mode.take() {
+            self.show_hitboxes = layout.show_hitboxes;
+            self.show_grid = layout.show_grid;
+            self.show_trim_preview = layout.show_trim_preview;
+            self.show_saved_cell = layout.show_saved_cell;
+            self.show_trim_comparison = layout.show_trim_comparison;
+            self.set_playback(layout.animation_running);
+        } else {
+            self.preview_mode = Some(EditLayout {
+                show_hitboxes: self.show_hitboxes,
+                show_grid: self.show_grid,
+                show_trim_preview: self.show_trim_preview,
+                show_saved_cell: self.show_saved_cell,
+                show_trim_comparison: self.show_trim_comparison,
+                animation_running: self.animation_running,
+            });
+            self.show_hitboxes = false;
+            self.show_grid = false;
+            self.show_trim_preview = false;
+            self.show_saved_cell = false;
+            self.show_trim_comparison = false;
+            self.set_playback(true);
+        }
+    }
+
+    /// Keeps `current_frame` pointing at an existing frame, or at 0 if there are none, and drops
+    /// selected hitboxes that no longer exist.
+    fn clamp_current_frame(&mut self) {
+        let frame_count = self.current_animation.timeline.frames.len();
+        self.current_frame = self.current_frame.min(frame_count.saturating_sub(1));
+        if self
+            .reference_frame
+            .is_some_and(|index| index >= frame_count)
+        {
+            self.reference_frame = None;
+        }
+        if self
+            .selected_frames
+            .is_some_and(|(_, end)| end >= frame_count)
+        {
+            self.selected_frames = None;
+        }
+        let hitboxes = &self.current_animation.hitboxes;
+        self.selected_boxes.retain(|id| hitboxes.contains_key(id));
+        if self
+            .currently_selected_box
+            .is_some_and(|id| !hitboxes.contains_key(&id))
+        {
+            self.currently_selected_box = None;
+        }
+    }
+
+    /// Keeps the reference frame on the same frame when one is inserted at `index`.
+    fn insert_reference_frame(&mut self, index: usize) {
+        if let Some(reference) = &mut self.reference_frame
+            && *reference >= index
+        {
+            *reference += 1;
+        }
+    }
+
+    /// Keeps the reference frame on the same frame when the one at `index` is removed, and
+    /// clears it if it was that frame.
+    fn remove_reference_frame(&mut self, index: usize) {
+        match self.reference_frame {
+            Some(reference) if reference == index => self.reference_frame = None,
+            Some(reference) if reference > index => self.reference_frame = Some(reference - 1),
+            _ => {}
+        }
+    }
+
+    /// How many undo or redo steps away the saved state is, or 0 if it is out of reach.
+    fn unsaved_edit_count(&self) -> usize {
+        self.edits_at_save.map_or(0, |n| {
+            (self.action_list.len() - self.undo_depth).abs_diff(n)
+        })
+    }
+
+    fn round_hitboxes(&mut self) {
+        let mut actions = vec![];
+        for (frame_index, frame) in self.current_animation.timeline.frames.iter().enumerate() {
+            for hp in frame.hitboxes.values() {
+                actions.push(Action::MoveHitbox {
+                    frame_index,
+                    id: hp.id,
+                    from: hp.pos,
+                    to: hp.pos.round(),
+                });
+                actions.push(Action::ResizeHitbox {
+                    frame_index,
+                    id: hp.id,
+                    from: hp.size,
+                    to: hp.size.round(),
+                });
+            }
+        }
+        actions.retain(Action::warrants_action);
+        self.do_action(Action::Group(actions));
+    }
+
+    fn round_offsets(&mut self) {
+        let mut actions = vec![];
+        for (frame_index, frame) in self.current_animation.timeline.frames.iter().enumerate() {
+            actions.push(Action::MoveSprite {
+                frame_index,
+                from: frame.offset,
+                to: frame.offset.round(),
+            });
+            actions.push(Action::SetMotionOffset {
+                frame_index,
+                from: frame.root_motion,
+                to: frame.root_motion.round(),
+            });
+        }
+        actions.retain(Action::warrants_action);
+        self.do_action(Action::Group(actions));
+    }
+
+    /// Moves each frame's root motion into its offset or the other way around, zeroing the
+    /// source, so the sprite ends up drawn at the same place while root motion is shown.
+    fn bake_motion(&mut self, bake: MotionBake, frames: RangeInclusive<usize>) {
+        let mut actions = vec![];
+        for frame_index in frames {
+            let Some(frame) = self.get_frame(frame_index) else {
+                continue;
+            };
+            let sign = Vec2::new(1.0, -1.0) * frame.flip_sign();
+            let (offset, root_motion) = match bake {
+                MotionBake::RootMotionToOffset => {
+                    (frame.offset - frame.root_motion * sign, Vec2::ZERO)
+                }
+                MotionBake::OffsetToRootMotion => {
+                    (Vec2::ZERO, frame.root_motion - frame.offset * sign)
+                }
+            };
+            actions.push(Action::MoveSprite {
+                frame_index,
+                from: frame.offset,
+                to: offset,
+            });
+            actions.push(Action::SetMotionOffset {
+                frame_index,
+                from: frame.root_motion,
+                to: root_motion,
+            });
+        }
+        actions.retain(Action::warrants_action);
+        self.do_action(Action::Group(actions));
+    }
+
+    /// Enables a hitbox on a frame. If the frame has no position for it yet, the
+    /// position from the closest earlier frame is used.
+    fn enable_hitbox(&mut self, frame_index: usize, id: usize) {
+        let toggle = Action::ToggleHitboxEnabled { frame_index, id };
+        if self.frame(frame_index).has_hitbox(id) {
+            self.do_action(toggle);
+            return;
+        }
+
+        let last_pos = self.current_animation.timeline.frames[..frame_index]
+            .iter()
+            .rev()
+            .find_map(|f| f.get_hitbox(id));
+        let hitbox_pos = if let Some(last_pos) = last_pos {
+            HitboxPos {
+                id,
+                pos: last_pos.pos,
+                size: last_pos.size,
+                enabled: false,
+            }
+        } else {
+            let size = self.last_hitbox_size;
+            let origin = self
+                .frame(frame_index)
+                .hitbox_origin(self.current_animation.anchor_relative_hitboxes);
+            HitboxPos {
+                id,
+                pos: size * Vec2::new(-0.5, 0.5) - origin,
+                size,
+                enabled: false,
+            }
+        };
+        self.do_action(Action::Group(vec![
+            Action::AddHitboxPos {
+                frame_index,
+                hitbox_pos,
+            },
+            toggle,
+        ]));
+    }
+
+    fn toggle_hitbox(&mut self, frame_index: usize, id: usize) {
+        if self.frame(frame_index).is_hitbox_enabled(id) {
+            self.do_action(Action::ToggleHitboxEnabled { frame_index, id });
+        } else {
+            self.enable_hitbox(frame_index, id);
+        }
+    }
+
+    /// The topmost hitbox of `frame_index` under `pos`, in display order.
+    fn hitbox_at(&self, frame_index: usize, pos: Vec2) -> Option<usize> {
+        let frame = self.get_frame(frame_index)?;
+        self.current_animation
+            .hitbox_order
+            .iter()
+            .rev()
+            .copied()
+            .find(|&id| {
+                frame
+                    .get_hitbox(id)
+                    .is_some_and(|hp| hp.rect().contains(pos))
+            })
+    }
+
+    /// Selects the enabled hitbox `step` places after the selected one in display order,
+    /// wrapping around.
+    fn cycle_selected_hitbox(&mut self, step: isize) {
+        let frame = self.frame(self.current_frame);
+        let enabled = self
+            .current_animation
+            .hitbox_order
+            .iter()
+            .copied()
+            .filter(|&id| frame.is_hitbox_enabled(id))
+            .collect::<Vec<_>>();
+        if enabled.is_empty() {
+            return;
+        }
+
+        let next = match self
+            .currently_selected_box
+            .and_then(|id| enabled.iter().position(|&i| i == id))
+        {
+            Some(position) => (position as isize + step).rem_euclid(enabled.len() as isize),
+            None if step < 0 => enabled.len() as isize - 1,
+            None => 0,
+        };
+        let id = enabled[next as usize];
+        self.currently_selected_box = Some(id);
+        self.selected_boxes = HashSet::from([id]);
+    }
+
+    /// Selects every enabled hitbox of the current frame, keeping the primary selection if it
+    /// is one of them.
+    fn select_all_hitboxes(&mut self) {
+        let Some(frame) = self.get_frame(self.current_frame) else {
+            return;
+        };
+        let enabled = self
+            .current_animation
+            .hitbox_order
+            .iter()
+            .copied()
+            .filter(|&id| frame.is_hitbox_enabled(id))
+            .collect::<Vec<_>>();
+        if !self
+            .currently_selected_box
+            .is_some_and(|id| enabled.contains(&id))
+        {
+            self.currently_selected_box = enabled.first().copied();
+        }
+        self.selected_boxes = enabled.into_iter().collect();
+    }
+
+    fn deselect_all_hitboxes(&mut self) {
+        self.currently_selected_box = None;
+        self.selected_boxes.clear();
+    }
+
+    /// Selects the enabled hitboxes of the current frame that aren't selected, and deselects
+    /// the ones that are.
+    fn invert_hitbox_selection(&mut self) {
+        let Some(frame) = self.get_frame(self.current_frame) else {
+            return;
+        };
+        let inverted = self
+            .current_animation
+            .hitbox_order
+            .iter()
+            .copied()
+            .filter(|&id| frame.is_hitbox_enabled(id) && !self.selected_boxes.contains(&id))
+            .collect::<Vec<_>>();
+        self.currently_selected_box = inverted.first().copied();
+        self.selected_boxes = inverted.into_iter().collect();
+    }
+
+    /// Looks for boxes that overlap in suspicious ways on every frame. Only enabled boxes
+    /// are compared.
+    fn check_hitboxes(&self) -> Vec<HitboxIssue> {
+        let hitboxes = &self.current_animation.hitboxes;
+        let mut issues = vec![];
+        for (frame_index, frame) in self.current_animation.timeline.frames.iter().enumerate() {
+            let enabled = self
+                .current_animation
+                .hitbox_order
+                .iter()
+                .filter_map(|id| frame.get_hitbox(*id))
+                .filter(|hp| hp.enabled)
+                .collect::<Vec<_>>();
+            for (i, a) in enabled.iter().enumerate() {
+                for b in &enabled[i + 1..] {
+                    if a.pos == b.pos && a.size == b.size {
+                        issues.push(HitboxIssue {
+                            frame_index,
+                            ids: [a.id, b.id],
+                            kind: HitboxIssueKind::Duplicate,
+                        });
+                        continue;
+                    }
+                    for (inner, outer) in [(a, b), (b, a)] {
+                        let (inner_rect, outer_rect) = (inner.rect(), outer.rect());
+                        if hitboxes[&inner.id].kind == BoxKind::Hurt
+                            && hitboxes[&outer.id].kind == BoxKind::Collision
+                            && outer_rect.contains(inner_rect.min)
+                            && outer_rect.contains(inner_rect.max)
+                        {
+                            issues.push(HitboxIssue {
+                                frame_index,
+                                ids: [inner.id, outer.id],
+                                kind: HitboxIssueKind::HurtboxInsideCollisionBox,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    /// Selects the enabled boxes of `kind` on the current frame.
+    fn select_hitboxes_by_kind(&mut self, kind: BoxKind) {
+        let Some(frame) = self.get_frame(self.current_frame) else {
+            return;
+        };
+        let hitboxes = &self.current_animation.hitboxes;
+        let matching = self
+            .current_animation
+            .hitbox_order
+            .iter()
+            .copied()
+            .filter(|&id| frame.is_hitbox_enabled(id) && hitboxes[&id].kind == kind)
+            .collect::<Vec<_>>();
+        self.currently_selected_box = matching.first().copied();
+        self.selected_boxes = matching.into_iter().collect();
+    }
+
+    /// Mirrors the selected hitboxes of the current frame, or all of them if none are
+    /// selected, across `axis` through the frame's anchor, as one undo step.
+    fn mirror_hitboxes(&mut self, axis: MirrorAxis) {
+        let Some(frame) = self.get_frame(self.current_frame) else {
+            return;
+        };
+        let anchor = -frame.hitbox_origin(self.current_animation.anchor_relative_hitboxes);
+        let selected = self
+            .selected_boxes
+            .iter()
+            .copied()
+            .chain(self.currently_selected_box)
+            .collect::<HashSet<_>>();
+
+        let mut actions = vec![];
+        for hp in frame.hitboxes.values() {
+            if !selected.is_empty() && !selected.contains(&hp.id) {
+                continue;
+            }
+            // `pos` is the top-left corner, so the far edge lands where it was.
+            let to = match axis {
+                MirrorAxis::Vertical => Vec2::new(2.0 * anchor.x - hp.pos.x - hp.size.x, hp.pos.y),
+                MirrorAxis::Horizontal => {
+                    Vec2::new(hp.pos.x, 2.0 * anchor.y - hp.pos.y + hp.size.y)
+                }
+            };
+            actions.push(Action::MoveHitbox {
+                frame_index: self.current_frame,
+                id: hp.id,
+                from: hp.pos,
+                to,
+            });
+        }
+        actions.retain(Action::warrants_action);
+        self.do_action(Action::Group(actions));
+    }
+
+    /// Moves a hitbox so its center is on the frame's anchor.
+    fn center_hitbox_on_anchor(&mut self, frame_index: usize, id: usize) {
+        let Some(hp) = self.get_frame(frame_index).and_then(|f| f.get_hitbox(id)) else {
+            return;
+        };
+        let anchor = -self
+            .frame(frame_index)
+            .hitbox_origin(self.current_animation.anchor_relative_hitboxes);
+        let action = Action::MoveHitbox {
+            frame_index,
+            id,
+            from: hp.pos,
+            to: anchor + hp.size * Vec2::new(-0.5, 0.5),
+        };
+        if action.warrants_action() {
+            self.do_action(action);
+        }
+    }
+
+    /// Moves the frame's anchor to the center of a hitbox, keeping every hitbox on the same
+    /// part of the image. Without anchor-relative hitboxes, that means moving them all too.
+    fn snap_anchor_to_hitbox(&mut self, frame_index: usize, id: usize) {
+        let Some(frame) = self.get_frame(frame_index) else {
+            return;
+        };
+        let Some(hp) = frame.get_hitbox(id) else {
+            return;
+        };
+        let center = hp.rect().center();
+
+        let mut actions = vec![];
+        if self.current_animation.anchor_relative_hitboxes {
+            actions.push(Action::MoveSprite {
+                frame_index,
+                from: frame.offset,
+                to: center * Vec2::new(1.0, -1.0),
+            });
+        } else {
+            actions.push(Action::MoveSprite {
+                frame_index,
+                from: frame.offset,
+                to: frame.offset + center * Vec2::new(1.0, -1.0),
+            });
+            for hp in frame.hitboxes.values() {
+                actions.push(Action::MoveHitbox {
+                    frame_index,
+                    id: hp.id,
+                    from: hp.pos,
+                    to: hp.pos - center,
+                });
+            }
+        }
+        actions.retain(Action::warrants_action);
+        self.do_action(Action::Group(actions));
+    }
+
+    /// Moves the edges of `rect` onto the closest edges of the other enabled hitboxes of
+    /// `frame_index` within `threshold`, per axis. Returns how far `rect` should move.
+    fn hitbox_snap(&self, frame_index: usize, id: usize, rect: Rect, threshold: f32) -> Vec2 {
+        let others = self
+            .frame(frame_index)
+            .hitboxes
+            .values()
+            .filter(|hp| hp.enabled && hp.id != id)
+            .map(HitboxPos::rect)
+            .collect::<Vec<_>>();
+        let snap_axis = |edges: [f32; 2], lines: Vec<f32>| {
+            edges
+                .into_iter()
+                .flat_map(|edge| lines.iter().map(move |line| line - edge))
+                .filter(|distance| distance.abs() <= threshold)
+                .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+                .unwrap_or(0.0)
+        };
+        Vec2::new(
+            snap_axis(
+                [rect.min.x, rect.max.x],
+                others.iter().flat_map(|r| [r.min.x, r.max.x]).collect(),
+            ),
+            snap_axis(
+                [rect.min.y, rect.max.y],
+                others.iter().flat_map(|r| [r.min.y, r.max.y]).collect(),
+            ),
+        )
+    }
+
+    /// Creates a box of `kind` that is enabled on `frame_index` only, and selects it.
+    fn create_hitbox_with_pos(&mut self, frame_index: usize, pos: Vec2, size: Vec2, kind: BoxKind) {
+        let id = self.next_hitbox_id();
+        let mut actions = vec![
+            Action::CreateHitbox {
+                id,
+                desc: format!("{} {id}", kind.label()),
+            },
+            Action::AddHitboxPos {
+                frame_index,
+                hitbox_pos: HitboxPos {
+                    id,
+                    pos,
+                    size,
+                    enabled: true,
+                },
+            },
+        ];
+        if kind != BoxKind::Hit {
+            actions.push(Action::SetHitboxKind {
+                id,
+                from: BoxKind::Hit,
+                to: kind,
+            });
+        }
+        self.do_action(Action::Group(actions));
+        self.currently_selected_box = Some(id);
+    }
+
+    /// Creates a hitbox right away, or opens the popup asking for its exact position and
+    /// size first if [`EditorState::ask_hitbox_size`] is set.
+    fn begin_hitbox_creation(
+        &mut self,
+        frame_index: usize,
+        pos: Vec2,
+        size: Vec2,
+        screen_pos: Option<Vec2>,
+    ) {
+        if self.ask_hitbox_size {
+            self.hitbox_draft = Some(HitboxDraft {
+                frame_index,
+                pos,
+                size,
+                screen_pos,
+            });
+            self.interaction_lock.lock_modal();
+        } else {
+            self.create_hitbox_with_pos(frame_index, pos, size, BoxKind::Hit);
+        }
+    }
+
+    /// Copies a hitbox, along with its position on every frame, and selects the copy.
+    fn duplicate_hitbox(&mut self, id: usize) {
+        let Some(hitbox) = self.current_animation.hitboxes.get(&id).cloned() else {
+            return;
+        };
+        let new_id = self.next_hitbox_id();
+        let mut actions = vec![
+            Action::CreateHitbox {
+                id: new_id,
+                desc: format!("{} (copy)", hitbox.desc),
+            },
+            Action::SetHitboxKind {
+                id: new_id,
+                from: BoxKind::Hit,
+                to: hitbox.kind,
+            },
+            Action::SetHitboxTag {
+                id: new_id,
+                from: String::new(),
+                to: hitbox.tag,
+            },
+        ];
+        for (frame_index, frame) in self.current_animation.timeline.frames.iter().enumerate() {
+            if let Some(hp) = frame.get_hitbox(id) {
+                actions.push(Action::AddHitboxPos {
+                    frame_index,
+                    hitbox_pos: HitboxPos {
+                        id: new_id,
+                        ..hp.clone()
+                    },
+                });
+            }
+        }
+        actions.retain(Action::warrants_action);
+        self.do_action(Action::Group(actions));
+        self.currently_selected_box = Some(new_id);
+        self.selected_boxes = HashSet::from([new_id]);
+    }
+
+    fn next_hitbox_id(&self) -> usize {
+        let mut id = 0;
+        while self.current_animation.hitboxes.contains_key(&id) {
+            id += 1;
+        }
+        id
+    }
+
+    /// The offset and root motion to display for the current frame, interpolated
+    /// towards the next frame during playback if motion tweening is enabled.
+    fn displayed_offsets(&self) -> Option<(Vec2, Vec2)> {
+        let frame = self.get_frame(self.current_frame)?;
+        if !(self.animation_running && self.interpolate_playback) {
+            return Some((frame.offset, frame.root_motion));
+        }
+
+        let frames = &self.current_animation.timeline.frames;
+        let next = &frames[(self.current_frame + 1) % frames.len()];
+        let t = self.frames_since_last_frame as f32 / frame.duration().max(1) as f32;
+        Some((
+            frame.offset.lerp(next.offset, t),
+            frame.root_motion.lerp(next.root_motion, t),
+        ))
+    }
+
+    /// The new descriptions of the selected hitboxes, in hitbox order, with every `{i}` in
+    /// `template` replaced by the box's position among them, counting from 0.
+    fn batch_rename_preview(&self, template: &str) -> Vec<(usize, String)> {
+        self.current_animation
+            .hitbox_order
+            .iter()
+            .filter(|id| self.selected_boxes.contains(id))
+            .enumerate()
+            .map(|(i, &id)| (id, template.replace("{i}", &i.to_string())))
+            .collect()
+    }
 
-        if let Some(action) = self.action_after_save.take() {
-            action(self);
+    /// Renames the selected hitboxes from a template as one undoable action; see
+    /// [`Self::batch_rename_preview`].
+    fn batch_rename_hitboxes(&mut self, template: &str) {
+        let mut actions = self
+            .batch_rename_preview(template)
+            .into_iter()
+            .map(|(id, to)| Action::RenameHitbox {
+                id,
+                from: self.current_animation.hitboxes[&id].desc.clone(),
+                to,
+            })
+            .collect::<Vec<_>>();
+        actions.retain(Action::warrants_action);
+        if !actions.is_empty() {
+            self.do_action(Action::Group(actions));
         }
     }
 
-    fn load(&mut self, path: impl AsRef<Path>, assets: &mut Assets<Image>) {
-        self.current_animation = load(&path, assets);
-        self.current_frame = 0;
-        self.current_basepath = Some(path.as_ref().to_string_lossy().to_string());
-        self.action_list = vec![];
-        self.has_saved = true;
+    /// Removes a hitbox definition together with its position on every frame.
+    fn delete_hitbox(&mut self, id: usize) {
+        let animation = &self.current_animation;
+        let Some(hitbox) = animation.hitboxes.get(&id).cloned() else {
+            return;
+        };
+        let Some(order_index) = animation.hitbox_order.iter().position(|&i| i == id) else {
+            return;
+        };
+        let positions = animation
+            .timeline
+            .frames
+            .iter()
+            .enumerate()
+            .filter_map(|(index, frame)| Some((index, frame.get_hitbox(id)?.clone())))
+            .collect();
+        if self.currently_selected_box == Some(id) {
+            self.currently_selected_box = None;
+        }
+        self.selected_boxes.remove(&id);
+        self.do_action(Action::DeleteHitbox {
+            id,
+            hitbox,
+            positions,
+            order_index,
+        });
     }
 
-    fn do_action(&mut self, action: Action) {
-        if action.warrants_action() {
-            for _ in 0..self.undo_depth {
-                self.action_list.pop().unwrap();
-            }
-            self.undo_depth = 0;
-            action.apply(self);
-            self.action_list.push(action);
-
-            self.has_saved = false;
+    /// Removes every frame, and optionally every hitbox definition, as one undoable action.
+    fn clear_frames(&mut self, keep_hitboxes: bool) {
+        let animation = &self.current_animation;
+        let mut actions = animation
+            .timeline
+            .frames
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, frame)| Action::RemoveFrame {
+                frame: frame.clone(),
+                index,
+                tags: animation.tags.clone(),
+            })
+            .collect::<Vec<_>>();
+        if !keep_hitboxes {
+            actions.extend(animation.hitbox_order.iter().enumerate().rev().map(
+                |(order_index, id)| Action::RemoveHitbox {
+                    hitbox: animation.hitboxes[id].clone(),
+                    order_index,
+                },
+            ));
+            self.currently_selected_box = None;
+            self.selected_boxes.clear();
         }
+        self.do_action(Action::Group(actions));
     }
 
-    fn undo(&mut self) {
-        if self.undo_depth >= self.action_list.len() {
+    /// Converts every hitbox position between the two hitbox origins; see
+    /// [`Frame::hitbox_origin`].
+    fn set_anchor_relative_hitboxes(&mut self, enabled: bool) {
+        let animation = &mut self.current_animation;
+        if animation.anchor_relative_hitboxes == enabled {
             return;
         }
-        self.undo_depth += 1;
-        let action = self.action_list[self.action_list.len() - self.undo_depth].clone();
-        action.reverse(self);
+        for frame in &mut animation.timeline.frames {
+            let shift = frame.hitbox_origin(true) * if enabled { -1.0 } else { 1.0 };
+            for hp in frame.hitboxes_mut().values_mut() {
+                hp.pos += shift;
+            }
+        }
+        animation.anchor_relative_hitboxes = enabled;
+    }
 
-        self.has_saved = false;
+    fn root_motion_visible(&self) -> bool {
+        self.selected_tool == Tool::MoveRootMotion || self.always_show_root_motion
     }
 
-    fn redo(&mut self) {
-        if self.undo_depth == 0 {
-            return;
+    /// Reads the current frame's pixel under `world_pos`, if there is one.
+    fn sample_pixel(&self, world_pos: Vec2, assets: &Assets<Image>) -> Option<SampledPixel> {
+        let (offset, root_motion) = self.displayed_offsets()?;
+        let frame = self.frame(self.current_frame);
+        let image = assets.get(&frame.image)?;
+        let translation = if self.root_motion_visible() {
+            root_motion
+        } else {
+            Vec2::ZERO
+        };
+        let image_pos = frame.world_to_image(translation, offset, world_pos);
+        if !(image_pos.x >= 0.0 && image_pos.y >= 0.0) {
+            return None;
+        }
+        let pos = image_pos.floor().as_uvec2();
+        let size = image.size().as_uvec2();
+        if pos.x >= size.x || pos.y >= size.y {
+            return None;
         }
 
-        let action = self.action_list[self.action_list.len() - self.undo_depth].clone();
-        action.apply(self);
-        self.undo_depth -= 1;
+        let rgba = match image.texture_descriptor.format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                let index = ((pos.y * size.x + pos.x) * 4) as usize;
+                image.data.get(index..index + 4)?.try_into().ok()?
+            }
+            _ => {
+                let image = image.clone().try_into_dynamic().ok()?;
+                image.to_rgba8().get_pixel(pos.x, pos.y).0
+            }
+        };
+        Some(SampledPixel { pos, rgba })
+    }
 
-        self.has_saved = false;
+    /// How the current frame's hitbox positions map to the world.
+    fn hitbox_space(&self) -> HitboxSpace {
+        let Some((offset, root_motion)) = self.displayed_offsets() else {
+            return HitboxSpace {
+                anchor: Vec2::ZERO,
+                origin: Vec2::ZERO,
+                flip: Vec2::ONE,
+                scale: Vec2::ONE,
+            };
+        };
+        let frame = self.frame(self.current_frame);
+        HitboxSpace {
+            anchor: if self.root_motion_visible() && self.hitboxes_follow_root_motion {
+                root_motion
+            } else {
+                Vec2::ZERO
+            },
+            origin: if self.current_animation.anchor_relative_hitboxes {
+                offset * Vec2::new(-1.0, 1.0)
+            } else {
+                Vec2::ZERO
+            },
+            flip: frame.flip_sign(),
+            scale: if self.current_animation.scale_hitboxes {
+                frame.scale
+            } else {
+                Vec2::ONE
+            },
+        }
     }
 
     fn get_frame(&self, index: usize) -> Option<&Frame> {
@@ -689,40 +2985,80 @@ fn on_close(
             editor_state.animation_running = false;
             editor_state.action_after_save = Some(Box::new(|es| es.exit_now = true));
             ui_state.show_save_menu = true;
-            editor_state.interaction_lock.lock_all();
+            editor_state.interaction_lock.lock_modal();
         }
     }
 }
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
 enum Tool {
     Select,
     MoveAnchor,
     MoveRootMotion,
     CreateHitbox,
     CreateHurtbox,
+    CreateCollisionbox,
     MoveSelected,
+    Eyedropper,
+}
+
+impl Tool {
+    /// The kind of box the tool draws, if it draws one.
+    fn box_kind(self) -> Option<BoxKind> {
+        match self {
+            Tool::CreateHitbox => Some(BoxKind::Hit),
+            Tool::CreateHurtbox => Some(BoxKind::Hurt),
+            Tool::CreateCollisionbox => Some(BoxKind::Collision),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
 enum Action {
+    /// Removes a frame. `tags` are the frame tags from before the removal, which can't
+    /// always be renumbered back.
     RemoveFrame {
         frame: Frame,
         index: usize,
+        tags: Vec<FrameTag>,
     },
     ChangeDelay {
         index: usize,
         from: usize,
         to: usize,
     },
+    ChangeRepeat {
+        index: usize,
+        from: u32,
+        to: u32,
+    },
     AddFrame {
         image: Handle<Image>,
     },
+    /// Inserts a copy of frame `index` right after it and moves to the copy.
+    DuplicateFrame {
+        index: usize,
+    },
+    InsertFrame {
+        frame: Frame,
+        index: usize,
+    },
     MoveSprite {
         frame_index: usize,
         from: Vec2,
         to: Vec2,
     },
+    ScaleSprite {
+        frame_index: usize,
+        from: Vec2,
+        to: Vec2,
+    },
+    RotateSprite {
+        frame_index: usize,
+        from: f32,
+        to: f32,
+    },
     SetMotionOffset {
         frame_index: usize,
         from: Vec2,
@@ -751,31 +3087,113 @@ enum Action {
     ToggleHitboxEnabled {
         frame_index: usize,
         id: usize,
-    }
+    },
+    AddHitboxPos {
+        frame_index: usize,
+        hitbox_pos: HitboxPos,
+    },
+    ChangeNote {
+        index: usize,
+        from: String,
+        to: String,
+    },
+    ReplaceFrame {
+        index: usize,
+        from: Box<Frame>,
+        to: Box<Frame>,
+    },
+    /// Mirrors a frame on the given axes. Applying it twice undoes it.
+    ToggleFlip {
+        frame_index: usize,
+        x: bool,
+        y: bool,
+    },
+    SetHitboxKind {
+        id: usize,
+        from: BoxKind,
+        to: BoxKind,
+    },
+    SetHitboxTag {
+        id: usize,
+        from: String,
+        to: String,
+    },
+    RenameHitbox {
+        id: usize,
+        from: String,
+        to: String,
+    },
+    /// Removes a hitbox definition at `order_index` in the display order. Its positions
+    /// in frames are left alone.
+    RemoveHitbox {
+        hitbox: Hitbox,
+        order_index: usize,
+    },
+    /// Removes a hitbox definition and its positions, as `(frame index, position)`
+    /// pairs, from every frame that had one.
+    DeleteHitbox {
+        id: usize,
+        hitbox: Hitbox,
+        positions: Vec<(usize, HitboxPos)>,
+        order_index: usize,
+    },
+    /// Switches whether hitbox positions are relative to each frame's anchor, converting
+    /// them so the boxes stay where they are.
+    SetAnchorRelativeHitboxes {
+        enabled: bool,
+    },
+    SetScaleHitboxes {
+        enabled: bool,
+    },
+    /// Moves an entry of the hitbox display order from one position to another.
+    ReorderHitbox {
+        from: usize,
+        to: usize,
+    },
+    SetFrameTags {
+        from: Vec<FrameTag>,
+        to: Vec<FrameTag>,
+    },
+    Group(Vec<Action>),
 }
 
 impl Action {
     fn apply(&self, state: &mut EditorState) {
         match self {
-            Action::RemoveFrame { frame, index } => {
+            Action::RemoveFrame { frame, index, tags } => {
                 let removed_frame = state.current_animation.timeline.frames.remove(*index);
                 assert!(*frame == removed_frame);
+                state.current_animation.remove_frame_from_tags(*index);
+                state.remove_reference_frame(*index);
                 if *index < state.current_frame {
                     state.current_frame -= 1;
                 }
-                if state.current_frame >= state.current_animation.timeline.frames.len()
-                    && state.current_frame != 0
-                {
-                    state.current_frame = state.current_animation.timeline.frames.len() - 1;
-                }
             }
-            Action::AddFrame { image } => state.current_animation.timeline.frames.push(Frame {
-                image: image.clone(),
-                offset: Vec2::ZERO,
-                root_motion: Vec2::ZERO,
-                delay: 1,
-                hitboxes: HashMap::new(),
-            }),
+            Action::AddFrame { image } => state
+                .current_animation
+                .timeline
+                .frames
+                .push(Frame::new(image.clone())),
+            Action::DuplicateFrame { index } => {
+                let frames = &mut state.current_animation.timeline.frames;
+                let copy = frames[*index].clone();
+                frames.insert(*index + 1, copy);
+                state
+                    .current_animation
+                    .insert_frame_in_tags(*index + 1, true);
+                state.insert_reference_frame(*index + 1);
+                state.current_frame = *index + 1;
+            }
+            Action::InsertFrame { frame, index } => {
+                state
+                    .current_animation
+                    .timeline
+                    .frames
+                    .insert(*index, frame.clone());
+                state.current_animation.insert_frame_in_tags(*index, false);
+                state.insert_reference_frame(*index);
+                state.current_frame = *index;
+            }
             Action::MoveSprite {
                 frame_index,
                 from,
@@ -783,11 +3201,34 @@ impl Action {
             } => {
                 state.current_animation.timeline.frames[*frame_index].offset = *to;
             }
+            Action::ScaleSprite {
+                frame_index,
+                from,
+                to,
+            } => {
+                state.current_animation.timeline.frames[*frame_index].scale = *to;
+            }
+            Action::RotateSprite {
+                frame_index,
+                from,
+                to,
+            } => {
+                state.current_animation.timeline.frames[*frame_index].rotation = *to;
+            }
             Action::ChangeDelay { index, from, to } => {
                 state.current_animation.timeline.frames[*index].delay = *to;
             }
+            Action::ChangeRepeat { index, from, to } => {
+                state.current_animation.timeline.frames[*index].repeat = *to;
+            }
             Action::SwapFrames { a, b } => {
                 state.current_animation.timeline.frames.swap(*a, *b);
+                state.current_animation.swap_frames_in_tags(*a, *b);
+                state.reference_frame = state.reference_frame.map(|index| match index {
+                    _ if index == *a => *b,
+                    _ if index == *b => *a,
+                    _ => index,
+                });
             }
             Action::SetMotionOffset {
                 frame_index,
@@ -802,9 +3243,11 @@ impl Action {
                     Hitbox {
                         id: *id,
                         desc: desc.clone(),
-                        is_hurtbox: false,
+                        kind: BoxKind::Hit,
+                        tag: String::new(),
                     },
                 );
+                state.current_animation.hitbox_order.push(*id);
             }
             Action::MoveHitbox {
                 frame_index: index,
@@ -829,31 +3272,114 @@ impl Action {
             Action::ToggleHitboxEnabled { frame_index, id } => {
                 state.current_animation.timeline.frames[*frame_index].hitbox_mut(*id).enabled.toggle();
             },
+            Action::AddHitboxPos {
+                frame_index,
+                hitbox_pos,
+            } => {
+                state.current_animation.timeline.frames[*frame_index]
+                    .hitboxes_mut()
+                    .insert(hitbox_pos.id, hitbox_pos.clone());
+            }
+            Action::ChangeNote { index, from, to } => {
+                state.current_animation.timeline.frames[*index].note = to.clone();
+            }
+            Action::ReplaceFrame { index, from, to } => {
+                state.current_animation.timeline.frames[*index] = (**to).clone();
+            }
+            Action::ReorderHitbox { from, to } => {
+                let order = &mut state.current_animation.hitbox_order;
+                let id = order.remove(*from);
+                order.insert(*to, id);
+            }
+            Action::ToggleFlip { frame_index, x, y } => {
+                state.frame_mut(*frame_index).toggle_flip(*x, *y);
+            }
+            Action::SetHitboxKind { id, from, to } => {
+                state.current_animation.hitboxes.get_mut(id).unwrap().kind = *to;
+            }
+            Action::SetHitboxTag { id, from, to } => {
+                state.current_animation.hitboxes.get_mut(id).unwrap().tag = to.clone();
+            }
+            Action::RenameHitbox { id, from, to } => {
+                state.current_animation.hitboxes.get_mut(id).unwrap().desc = to.clone();
+            }
+            Action::RemoveHitbox {
+                hitbox,
+                order_index,
+            } => {
+                state.current_animation.hitboxes.remove(&hitbox.id);
+                let id = state.current_animation.hitbox_order.remove(*order_index);
+                assert!(id == hitbox.id);
+            }
+            Action::DeleteHitbox {
+                id,
+                hitbox,
+                positions,
+                order_index,
+            } => {
+                let animation = &mut state.current_animation;
+                animation.hitboxes.remove(id);
+                let removed = animation.hitbox_order.remove(*order_index);
+                assert!(removed == *id);
+                for (frame_index, _) in positions {
+                    animation.timeline.frames[*frame_index]
+                        .hitboxes_mut()
+                        .remove(id);
+                }
+            }
+            Action::SetAnchorRelativeHitboxes { enabled } => {
+                state.set_anchor_relative_hitboxes(*enabled);
+            }
+            Action::SetScaleHitboxes { enabled } => {
+                state.current_animation.scale_hitboxes = *enabled;
+            }
+            Action::SetFrameTags { from, to } => {
+                state.current_animation.tags = to.clone();
+            }
+            Action::Group(actions) => {
+                for action in actions {
+                    action.apply(state);
+                }
+            }
         }
     }
 
     fn reverse(&self, state: &mut EditorState) {
         match self {
-            Action::RemoveFrame { frame, index } => {
+            Action::RemoveFrame { frame, index, tags } => {
                 state
                     .current_animation
                     .timeline
                     .frames
                     .insert(*index, frame.clone());
+                state.current_animation.tags = tags.clone();
+                state.insert_reference_frame(*index);
                 if state.current_frame >= *index
                     && state.current_animation.timeline.frames.len() != 1
                 {
                     state.current_frame += 1;
                 }
             }
+            Action::DuplicateFrame { index } => {
+                state.current_animation.timeline.frames.remove(*index + 1);
+                state.current_animation.remove_frame_from_tags(*index + 1);
+                state.remove_reference_frame(*index + 1);
+                if state.current_frame > *index {
+                    state.current_frame -= 1;
+                }
+            }
+            Action::InsertFrame { frame, index } => {
+                let removed_frame = state.current_animation.timeline.frames.remove(*index);
+                assert!(*frame == removed_frame);
+                state.current_animation.remove_frame_from_tags(*index);
+                state.remove_reference_frame(*index);
+                if state.current_frame > *index {
+                    state.current_frame -= 1;
+                }
+            }
             Action::AddFrame { image } => {
                 let frame = state.current_animation.timeline.frames.pop().unwrap();
                 assert!(frame.image == *image);
-                if state.current_frame >= state.current_animation.timeline.frames.len()
-                    && state.current_frame != 0
-                {
-                    state.current_frame = state.current_animation.timeline.frames.len() - 1;
-                }
             }
             Action::MoveSprite {
                 frame_index,
@@ -862,11 +3388,34 @@ impl Action {
             } => {
                 state.current_animation.timeline.frames[*frame_index].offset = *from;
             }
+            Action::ScaleSprite {
+                frame_index,
+                from,
+                to,
+            } => {
+                state.current_animation.timeline.frames[*frame_index].scale = *from;
+            }
+            Action::RotateSprite {
+                frame_index,
+                from,
+                to,
+            } => {
+                state.current_animation.timeline.frames[*frame_index].rotation = *from;
+            }
             Action::ChangeDelay { index, from, to } => {
                 state.current_animation.timeline.frames[*index].delay = *from;
             }
+            Action::ChangeRepeat { index, from, to } => {
+                state.current_animation.timeline.frames[*index].repeat = *from;
+            }
             Action::SwapFrames { a, b } => {
                 state.current_animation.timeline.frames.swap(*a, *b);
+                state.current_animation.swap_frames_in_tags(*a, *b);
+                state.reference_frame = state.reference_frame.map(|index| match index {
+                    _ if index == *a => *b,
+                    _ if index == *b => *a,
+                    _ => index,
+                });
             }
             Action::SetMotionOffset {
                 frame_index,
@@ -877,6 +3426,7 @@ impl Action {
             }
             Action::CreateHitbox { id, desc } => {
                 state.current_animation.hitboxes.remove(id);
+                state.current_animation.hitbox_order.retain(|i| i != id);
             }
             Action::MoveHitbox {
                 frame_index: index,
@@ -901,19 +3451,218 @@ impl Action {
             Action::ToggleHitboxEnabled { frame_index, id } => {
                 state.current_animation.timeline.frames[*frame_index].hitbox_mut(*id).enabled.toggle();
             },
+            Action::AddHitboxPos {
+                frame_index,
+                hitbox_pos,
+            } => {
+                state.current_animation.timeline.frames[*frame_index]
+                    .hitboxes_mut()
+                    .remove(&hitbox_pos.id);
+            }
+            Action::ChangeNote { index, from, to } => {
+                state.current_animation.timeline.frames[*index].note = from.clone();
+            }
+            Action::ReplaceFrame { index, from, to } => {
+                state.current_animation.timeline.frames[*index] = (**from).clone();
+            }
+            Action::ReorderHitbox { from, to } => {
+                let order = &mut state.current_animation.hitbox_order;
+                let id = order.remove(*to);
+                order.insert(*from, id);
+            }
+            Action::ToggleFlip { frame_index, x, y } => {
+                state.frame_mut(*frame_index).toggle_flip(*x, *y);
+            }
+            Action::SetHitboxKind { id, from, to } => {
+                state.current_animation.hitboxes.get_mut(id).unwrap().kind = *from;
+            }
+            Action::SetHitboxTag { id, from, to } => {
+                state.current_animation.hitboxes.get_mut(id).unwrap().tag = from.clone();
+            }
+            Action::RenameHitbox { id, from, to } => {
+                state.current_animation.hitboxes.get_mut(id).unwrap().desc = from.clone();
+            }
+            Action::RemoveHitbox {
+                hitbox,
+                order_index,
+            } => {
+                state
+                    .current_animation
+                    .hitboxes
+                    .insert(hitbox.id, hitbox.clone());
+                state
+                    .current_animation
+                    .hitbox_order
+                    .insert(*order_index, hitbox.id);
+            }
+            Action::DeleteHitbox {
+                id,
+                hitbox,
+                positions,
+                order_index,
+            } => {
+                let animation = &mut state.current_animation;
+                animation.hitboxes.insert(*id, hitbox.clone());
+                animation.hitbox_order.insert(*order_index, *id);
+                for (frame_index, hitbox_pos) in positions {
+                    animation.timeline.frames[*frame_index]
+                        .hitboxes_mut()
+                        .insert(*id, hitbox_pos.clone());
+                }
+            }
+            Action::SetAnchorRelativeHitboxes { enabled } => {
+                state.set_anchor_relative_hitboxes(!*enabled);
+            }
+            Action::SetScaleHitboxes { enabled } => {
+                state.current_animation.scale_hitboxes = !*enabled;
+            }
+            Action::SetFrameTags { from, to } => {
+                state.current_animation.tags = from.clone();
+            }
+            Action::Group(actions) => {
+                for action in actions.iter().rev() {
+                    action.reverse(state);
+                }
+            }
+        }
+    }
+
+    /// The single action doing `self` and then `next`, if both change the same value.
+    fn try_merge(&self, next: &Action) -> Option<Action> {
+        match (self, next) {
+            (
+                Action::ChangeDelay { index, from, .. },
+                Action::ChangeDelay {
+                    index: next_index,
+                    to,
+                    ..
+                },
+            ) if index == next_index => Some(Action::ChangeDelay {
+                index: *index,
+                from: *from,
+                to: *to,
+            }),
+            (
+                Action::ChangeRepeat { index, from, .. },
+                Action::ChangeRepeat {
+                    index: next_index,
+                    to,
+                    ..
+                },
+            ) if index == next_index => Some(Action::ChangeRepeat {
+                index: *index,
+                from: *from,
+                to: *to,
+            }),
+            (
+                Action::MoveSprite {
+                    frame_index, from, ..
+                },
+                Action::MoveSprite {
+                    frame_index: next_index,
+                    to,
+                    ..
+                },
+            ) if frame_index == next_index => Some(Action::MoveSprite {
+                frame_index: *frame_index,
+                from: *from,
+                to: *to,
+            }),
+            (
+                Action::SetMotionOffset {
+                    frame_index, from, ..
+                },
+                Action::SetMotionOffset {
+                    frame_index: next_index,
+                    to,
+                    ..
+                },
+            ) if frame_index == next_index => Some(Action::SetMotionOffset {
+                frame_index: *frame_index,
+                from: *from,
+                to: *to,
+            }),
+            (
+                Action::MoveHitbox {
+                    frame_index,
+                    id,
+                    from,
+                    ..
+                },
+                Action::MoveHitbox {
+                    frame_index: next_index,
+                    id: next_id,
+                    to,
+                    ..
+                },
+            ) if (frame_index, id) == (next_index, next_id) => Some(Action::MoveHitbox {
+                frame_index: *frame_index,
+                id: *id,
+                from: *from,
+                to: *to,
+            }),
+            (
+                Action::ResizeHitbox {
+                    frame_index,
+                    id,
+                    from,
+                    ..
+                },
+                Action::ResizeHitbox {
+                    frame_index: next_index,
+                    id: next_id,
+                    to,
+                    ..
+                },
+            ) if (frame_index, id) == (next_index, next_id) => Some(Action::ResizeHitbox {
+                frame_index: *frame_index,
+                id: *id,
+                from: *from,
+                to: *to,
+            }),
+            (Action::SetFrameTags { from, .. }, Action::SetFrameTags { to, .. }) => {
+                Some(Action::SetFrameTags {
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The hitbox size this action creates or resizes to, if any.
+    fn hitbox_size(&self) -> Option<Vec2> {
+        match self {
+            Action::ResizeHitbox { to, .. } => Some(*to),
+            Action::AddHitboxPos { hitbox_pos, .. } => Some(hitbox_pos.size),
+            Action::Group(actions) => actions.iter().rev().find_map(Action::hitbox_size),
+            _ => None,
         }
     }
 
     fn warrants_action(&self) -> bool {
         match self {
-            Action::RemoveFrame { frame, index } => true,
+            Action::RemoveFrame { frame, index, tags } => true,
             Action::ChangeDelay { index, from, to } => from != to,
+            Action::ChangeRepeat { index, from, to } => from != to,
             Action::AddFrame { image } => true,
+            Action::DuplicateFrame { index } => true,
+            Action::InsertFrame { frame, index } => true,
             Action::MoveSprite {
                 frame_index,
                 from,
                 to,
             } => from != to,
+            Action::ScaleSprite {
+                frame_index,
+                from,
+                to,
+            } => from != to,
+            Action::RotateSprite {
+                frame_index,
+                from,
+                to,
+            } => from != to,
             Action::SetMotionOffset {
                 frame_index,
                 from,
@@ -934,16 +3683,136 @@ impl Action {
                 to,
             } => from != to,
             Action::ToggleHitboxEnabled { frame_index, id } => true,
+            Action::AddHitboxPos {
+                frame_index,
+                hitbox_pos,
+            } => true,
+            Action::ChangeNote { index, from, to } => from != to,
+            Action::ReplaceFrame { index, from, to } => from != to,
+            Action::ReorderHitbox { from, to } => from != to,
+            Action::SetFrameTags { from, to } => from != to,
+            Action::ToggleFlip { frame_index, x, y } => *x || *y,
+            Action::SetHitboxKind { id, from, to } => from != to,
+            Action::SetHitboxTag { id, from, to } => from != to,
+            Action::RenameHitbox { id, from, to } => from != to,
+            Action::RemoveHitbox {
+                hitbox,
+                order_index,
+            } => true,
+            Action::DeleteHitbox {
+                id,
+                hitbox,
+                positions,
+                order_index,
+            } => true,
+            Action::SetAnchorRelativeHitboxes { enabled } => true,
+            Action::SetScaleHitboxes { enabled } => true,
+            Action::Group(actions) => actions.iter().any(Action::warrants_action),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct AnimationFileData {
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct AnimationFileData {
+    #[serde(with = "seethe")]
+    #[schemars(with = "String")]
+    spritesheet: Vec<u8>,
+    /// Additional atlas pages when multi-page packing was needed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra_pages: Vec<Spritesheet>,
+    info: Info,
+    /// How the editor was last set up for this file. Not gameplay data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    editor_view: Option<EditorView>,
+}
+
+/// Editor-only view settings saved with an animation, so reopening it resumes where it was
+/// left off.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+struct EditorView {
+    #[serde(deserialize_with = "deserialize_tool")]
+    selected_tool: Tool,
+    show_hitboxes: bool,
+    always_show_root_motion: bool,
+    #[serde(default = "default_true")]
+    hitboxes_follow_root_motion: bool,
+    /// Center of the view, in world units.
+    #[schemars(with = "[f32; 2]")]
+    camera_position: Vec2,
+    /// World units per screen pixel.
+    zoom: f32,
+}
+
+/// A named bundle of view settings, for switching between editing tasks such as placing
+/// hitboxes and reviewing timing.
+#[derive(Serialize, Deserialize, Clone)]
+struct WorkspacePreset {
+    name: String,
+    view: EditorView,
+    show_grid: bool,
+    grid_at_anchor: bool,
+    show_trim_preview: bool,
+    show_saved_cell: bool,
+    marker_size: f32,
+    sprite_tint: [f32; 4],
+}
+
+/// Name of the file workspace presets are kept in, in the editor's [`config_dir`].
+const WORKSPACE_PRESETS_FILE: &str = "workspace_presets.json";
+
+/// The current user's folder for the editor's own files, where each platform keeps settings.
+fn config_dir() -> Option<PathBuf> {
+    let var = |name| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(windows) {
+        var("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        var("HOME")?.join("Library/Application Support")
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| Some(var("HOME")?.join(".config")))?
+    };
+    Some(base.join("fg-anim-editor"))
+}
+
+fn workspace_presets_path() -> Option<PathBuf> {
+    Some(config_dir()?.join(WORKSPACE_PRESETS_FILE))
+}
+
+/// Reads the saved workspace presets. A missing file gives no presets.
+fn load_workspace_presets() -> Result<Vec<WorkspacePreset>, String> {
+    let Some(path) = workspace_presets_path() else {
+        return Ok(vec![]);
+    };
+    let Ok(file) = std::fs::File::open(&path) else {
+        return Ok(vec![]);
+    };
+    serde_json::from_reader(file).map_err(|err| {
+        format!(
+            "Could not read workspace presets from {}: {err}",
+            path.display()
+        )
+    })
+}
+
+fn write_workspace_presets(presets: &[WorkspacePreset]) -> Result<(), Box<dyn Error>> {
+    let path = workspace_presets_path().ok_or("could not locate the user's config folder")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    serde_json::to_writer_pretty(std::fs::File::create(path)?, presets)?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+struct Spritesheet(
     #[serde(with = "seethe")]
-    spritesheet: Vec<u8>,
-    info: Info,
-}
+    #[schemars(with = "String")]
+    Vec<u8>,
+);
 
 mod seethe {
     use base64::Engine;
@@ -985,9 +3854,9 @@ mod seethe {
             where
                 E: serde::de::Error,
             {
-                Ok(base64::engine::general_purpose::STANDARD_NO_PAD
+                base64::engine::general_purpose::STANDARD_NO_PAD
                     .decode(v)
-                    .unwrap())
+                    .map_err(E::custom)
             }
         }
 
@@ -999,7 +3868,7 @@ mod seethe {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 struct Info {
     cell_width: usize,
     cell_height: usize,
@@ -1007,26 +3876,362 @@ struct Info {
     frame_count: usize,
     frame_data: Vec<FrameData>,
     hitboxes: HashMap<usize, Hitbox>,
+    /// Display order of the hitbox ids.
+    #[serde(default)]
+    hitbox_order: Vec<usize>,
+    /// Whether hitbox positions move with the sprite when a frame's `origin` changes.
+    /// See [`HitboxPos::pos`].
+    #[serde(default)]
+    anchor_relative_hitboxes: bool,
+    /// Whether hitboxes are scaled along with each frame's `scale`, around the anchor.
+    #[serde(default)]
+    scale_hitboxes: bool,
+    /// For each hitbox id, the inclusive `[start, end]` frame ranges it is enabled on. Derived
+    /// from `frame_data` on save for engines that consume active windows; ignored on load.
+    #[serde(default)]
+    active_windows: HashMap<usize, Vec<[usize; 2]>>,
+    #[serde(default)]
+    tags: Vec<FrameTag>,
+    #[serde(default)]
+    metadata: Metadata,
+    /// Tick rate in ticks per second; each frame's `delay` counts these ticks. Used for
+    /// playback and for every conversion between ticks and time.
+    #[serde(default = "default_fps")]
+    fps: u32,
+    /// Which way y points in `frame_data`. Files from before this setting are y-up.
+    #[serde(default)]
+    y_axis: YAxis,
 }
 
-#[derive(Serialize, Deserialize)]
+impl Info {
+    /// Converts `frame_data` from the file's `y_axis` into the editor's y-up coordinates.
+    fn into_y_up(mut self) -> Self {
+        let y_axis = self.y_axis;
+        self.frame_data = std::mem::take(&mut self.frame_data)
+            .into_iter()
+            .map(|frame_data| frame_data.in_y_axis(y_axis))
+            .collect();
+        self
+    }
+}
+
+/// Which way y points in a file's frame coordinates, for engines with either convention.
+///
+/// The editor works y-up: root motion and hitbox positions are y-up, and a hitbox's `pos` is
+/// its top-left corner. Writing y-down flips the y of root motion and of hitbox positions,
+/// which stay the top-left corner, and the sign of rotation, so it turns the same way on
+/// screen. A frame's `origin` is a pixel of its image, counted y-down from the top-left, in
+/// both conventions.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+enum YAxis {
+    #[default]
+    Up,
+    Down,
+}
+
+impl YAxis {
+    fn label(self) -> &'static str {
+        match self {
+            YAxis::Up => "Y up",
+            YAxis::Down => "Y down",
+        }
+    }
+}
+
+fn default_fps() -> u32 {
+    60
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Deserializes a tool, falling back to [`Tool::Select`] for one this version doesn't know,
+/// e.g. from a newer editor, so that the rest of the file still loads.
+fn deserialize_tool<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Tool, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(Tool::deserialize(value).unwrap_or(Tool::Select))
+}
+
+fn default_repeat() -> u32 {
+    1
+}
+
+fn default_scale() -> Vec2 {
+    Vec2::ONE
+}
+
+/// Descriptive information about an animation, for organizing libraries and for importers.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default)]
+struct Metadata {
+    name: String,
+    author: String,
+    tags: Vec<String>,
+}
+
+/// A named, colored range of frames, e.g. the startup or active frames of a move.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+struct FrameTag {
+    name: String,
+    color: [u8; 3],
+    start: usize,
+    /// The last frame of the tag, inclusive.
+    end: usize,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 struct FrameData {
     delay: usize,
+    /// How many times the frame is played in a row, for held sub-loops. The frame is shown
+    /// for `delay * repeat` ticks in total.
+    #[serde(default = "default_repeat")]
+    repeat: u32,
+    #[schemars(with = "[f32; 2]")]
     origin: Vec2,
+    #[schemars(with = "[f32; 2]")]
     root_motion: Vec2,
     hitboxes: HashMap<usize, HitboxPos>,
+    #[serde(default)]
+    note: String,
+    /// Display the image, and the hitboxes, mirrored around the origin.
+    #[serde(default)]
+    flip_x: bool,
+    #[serde(default)]
+    flip_y: bool,
+    /// Scale of the sprite around the origin.
+    #[serde(default = "default_scale")]
+    #[schemars(with = "[f32; 2]")]
+    scale: Vec2,
+    /// Counterclockwise rotation of the sprite around the origin, in degrees. Hitboxes
+    /// stay axis-aligned.
+    #[serde(default)]
+    rotation: f32,
+    /// Location of the frame in the atlas. Absent for single-page grid
+    /// spritesheets, where the location follows from the frame index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rect: Option<FrameRect>,
+    /// File of the frame's image in a PNG sequence, relative to its folder unless absolute.
+    /// Absent for the usual `frame_NNN.png`, and in `.anim` files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+}
+
+impl FrameData {
+    /// Converts between the editor's y-up coordinates and `y_axis`, see [`YAxis`]. The
+    /// conversion is its own inverse, so it is used for both reading and writing.
+    fn in_y_axis(mut self, y_axis: YAxis) -> Self {
+        if y_axis == YAxis::Down {
+            self.root_motion.y = -self.root_motion.y;
+            self.rotation = -self.rotation;
+            for hp in self.hitboxes.values_mut() {
+                hp.pos.y = -hp.pos.y;
+            }
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+struct FrameRect {
+    page: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
 }
 
 struct Animation {
     timeline: Timeline,
     hitboxes: HashMap<usize, Hitbox>,
+    /// Display order of the hitboxes; later boxes are drawn and picked on top.
+    hitbox_order: Vec<usize>,
+    anchor_relative_hitboxes: bool,
+    scale_hitboxes: bool,
+    tags: Vec<FrameTag>,
+    metadata: Metadata,
+    fps: u32,
+    /// The y convention the animation is saved and exported in.
+    y_axis: YAxis,
 }
 
 impl Animation {
+    /// How long one tick lasts at the animation's tick rate. Every conversion between ticks
+    /// and time goes through this.
+    fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps.max(1) as f64)
+    }
+
+    fn ticks_to_millis(&self, ticks: usize) -> f64 {
+        self.tick_duration().as_secs_f64() * 1000.0 * ticks as f64
+    }
+
+    /// Total length of one playthrough, in ticks.
+    fn total_ticks(&self) -> usize {
+        self.timeline.frames.iter().map(Frame::duration).sum()
+    }
+
+    /// The runs of consecutive frames each hitbox is enabled on, as inclusive `[start, end]`
+    /// frame indices. Hitboxes that are never enabled get no windows.
+    fn active_windows(&self) -> HashMap<usize, Vec<[usize; 2]>> {
+        let mut windows = HashMap::new();
+        for &id in self.hitboxes.keys() {
+            let mut runs: Vec<[usize; 2]> = vec![];
+            for (index, frame) in self.timeline.frames.iter().enumerate() {
+                if !frame.is_hitbox_enabled(id) {
+                    continue;
+                }
+                match runs.last_mut() {
+                    Some([_, end]) if *end + 1 == index => *end = index,
+                    _ => runs.push([index, index]),
+                }
+            }
+            if !runs.is_empty() {
+                windows.insert(id, runs);
+            }
+        }
+        windows
+    }
+
+    /// A copy in which each run of consecutive frames showing the same thing is merged into
+    /// its first frame, played for the run's total duration. Runs are not merged across tag
+    /// boundaries, and tags are renumbered to match.
+    fn collapse_held_frames(&self) -> Animation {
+        let mut frames: Vec<Frame> = vec![];
+        let mut new_index = vec![];
+        for (index, frame) in self.timeline.frames.iter().enumerate() {
+            let at_tag_boundary = self
+                .tags
+                .iter()
+                .any(|tag| tag.start == index || tag.end + 1 == index);
+            match frames.last_mut() {
+                Some(last) if !at_tag_boundary && last.looks_like(frame) => {
+                    last.delay = last.duration() + frame.duration();
+                    last.repeat = 1;
+                }
+                _ => frames.push(frame.clone()),
+            }
+            new_index.push(frames.len() - 1);
+        }
+
+        // Tags loaded from a file may reach past the last frame; those that start past it are
+        // dropped and the rest are cut off at the end.
+        let tags = self
+            .tags
+            .iter()
+            .filter_map(|tag| {
+                let start = *new_index.get(tag.start)?;
+                let end = new_index
+                    .get(tag.end)
+                    .or(new_index.last())
+                    .copied()?
+                    .max(start);
+                Some(FrameTag {
+                    start,
+                    end,
+                    ..tag.clone()
+                })
+            })
+            .collect();
+
+        Animation {
+            timeline: Timeline { frames },
+            hitboxes: self.hitboxes.clone(),
+            hitbox_order: self.hitbox_order.clone(),
+            anchor_relative_hitboxes: self.anchor_relative_hitboxes,
+            scale_hitboxes: self.scale_hitboxes,
+            tags,
+            metadata: self.metadata.clone(),
+            fps: self.fps,
+            y_axis: self.y_axis,
+        }
+    }
+
+    fn from_info(frames: Vec<Frame>, info: Info) -> Self {
+        let mut animation = Animation {
+            timeline: Timeline { frames },
+            hitboxes: info.hitboxes,
+            hitbox_order: info.hitbox_order,
+            anchor_relative_hitboxes: info.anchor_relative_hitboxes,
+            scale_hitboxes: info.scale_hitboxes,
+            tags: info.tags,
+            metadata: info.metadata,
+            fps: info.fps,
+            y_axis: info.y_axis,
+        };
+        animation.normalize_hitbox_order();
+        animation
+    }
+
     fn new() -> Self {
         Self {
             timeline: Timeline { frames: vec![] },
             hitboxes: HashMap::new(),
+            hitbox_order: vec![],
+            anchor_relative_hitboxes: false,
+            scale_hitboxes: false,
+            tags: vec![],
+            metadata: Metadata::default(),
+            y_axis: YAxis::Up,
+            fps: default_fps(),
+        }
+    }
+
+    /// Drops unknown ids from `hitbox_order` and appends any missing ones in id order.
+    fn normalize_hitbox_order(&mut self) {
+        let hitboxes = &self.hitboxes;
+        self.hitbox_order.retain(|id| hitboxes.contains_key(id));
+        let mut missing = hitboxes
+            .keys()
+            .copied()
+            .filter(|id| !self.hitbox_order.contains(id))
+            .collect::<Vec<_>>();
+        missing.sort();
+        self.hitbox_order.extend(missing);
+    }
+
+    /// Renumbers the tags for a frame inserted at `index`. Tags spanning the insertion point
+    /// grow to cover the new frame, as do tags ending right before it if `extends_previous`.
+    fn insert_frame_in_tags(&mut self, index: usize, extends_previous: bool) {
+        for tag in &mut self.tags {
+            if tag.start >= index {
+                tag.start += 1;
+            }
+            if tag.end >= index || (extends_previous && tag.end + 1 == index) {
+                tag.end += 1;
+            }
+        }
+    }
+
+    /// Renumbers the tags for the frame at `index` being removed. Tags covering only that
+    /// frame are dropped.
+    fn remove_frame_from_tags(&mut self, index: usize) {
+        self.tags
+            .retain(|tag| (tag.start, tag.end) != (index, index));
+        for tag in &mut self.tags {
+            if tag.start > index {
+                tag.start -= 1;
+            }
+            if tag.end >= index {
+                tag.end -= 1;
+            }
+        }
+    }
+
+    /// Renumbers the tags for frames `a` and `b` trading places, so that tag ends follow
+    /// their frames. Tags whose ends would cross are left alone, which keeps this its own
+    /// inverse.
+    fn swap_frames_in_tags(&mut self, a: usize, b: usize) {
+        let swapped = |index: usize| match index {
+            _ if index == a => b,
+            _ if index == b => a,
+            _ => index,
+        };
+        for tag in &mut self.tags {
+            let (start, end) = (swapped(tag.start), swapped(tag.end));
+            if start <= end {
+                (tag.start, tag.end) = (start, end);
+            }
         }
     }
 }
@@ -1037,10 +4242,165 @@ struct Frame {
     offset: Vec2,
     root_motion: Vec2,
     delay: usize,
-    hitboxes: HashMap<usize, HitboxPos>,
+    repeat: u32,
+    /// Shared with the frame snapshots in the undo history until it's modified.
+    hitboxes: Arc<HashMap<usize, HitboxPos>>,
+    note: String,
+    flip_x: bool,
+    flip_y: bool,
+    scale: Vec2,
+    rotation: f32,
+}
+
+/// `scale`, or 1 if it is zero or not finite and so can't be divided by.
+fn usable_scale(scale: f32) -> f32 {
+    if scale.is_finite() && scale != 0.0 {
+        scale
+    } else {
+        1.0
+    }
 }
 
 impl Frame {
+    fn new(image: Handle<Image>) -> Self {
+        Self {
+            image,
+            offset: Vec2::ZERO,
+            root_motion: Vec2::ZERO,
+            delay: 1,
+            repeat: 1,
+            hitboxes: default(),
+            note: String::new(),
+            flip_x: false,
+            flip_y: false,
+            scale: Vec2::ONE,
+            rotation: 0.0,
+        }
+    }
+
+    fn from_frame_data(image: Handle<Image>, frame_data: &FrameData) -> Self {
+        Self {
+            image,
+            offset: frame_data.origin,
+            root_motion: frame_data.root_motion,
+            delay: frame_data.delay,
+            repeat: frame_data.repeat,
+            hitboxes: Arc::new(frame_data.hitboxes.clone()),
+            note: frame_data.note.clone(),
+            flip_x: frame_data.flip_x,
+            flip_y: frame_data.flip_y,
+            // Hitbox space divides by the scale, so one that can't be undone is reset.
+            scale: Vec2::new(
+                usable_scale(frame_data.scale.x),
+                usable_scale(frame_data.scale.y),
+            ),
+            rotation: frame_data.rotation,
+        }
+    }
+
+    fn to_frame_data(&self) -> FrameData {
+        FrameData {
+            delay: self.delay,
+            repeat: self.repeat,
+            origin: self.offset,
+            root_motion: self.root_motion,
+            hitboxes: (*self.hitboxes).clone(),
+            note: self.note.clone(),
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+            scale: self.scale,
+            rotation: self.rotation,
+            rect: None,
+            image: None,
+        }
+    }
+
+    /// Number of ticks the frame is shown for, counting its repeats.
+    fn duration(&self) -> usize {
+        self.delay * self.repeat.max(1) as usize
+    }
+
+    /// Whether both frames look the same, ignoring how long they are shown.
+    fn looks_like(&self, other: &Frame) -> bool {
+        *other
+            == Frame {
+                delay: other.delay,
+                repeat: other.repeat,
+                ..self.clone()
+            }
+    }
+
+    fn toggle_flip(&mut self, x: bool, y: bool) {
+        self.flip_x ^= x;
+        self.flip_y ^= y;
+    }
+
+    /// `-1` on each axis the frame is mirrored on, `1` otherwise.
+    fn flip_sign(&self) -> Vec2 {
+        Vec2::new(
+            if self.flip_x { -1.0 } else { 1.0 },
+            if self.flip_y { -1.0 } else { 1.0 },
+        )
+    }
+
+    /// Maps a point in the world to the image the way the sprite is drawn: the image point
+    /// `offset` at `translation`, flipped, scaled and rotated around it.
+    fn world_to_image(&self, translation: Vec2, offset: Vec2, world_pos: Vec2) -> Vec2 {
+        let sprite_pos = (Quat::from_rotation_z(-self.rotation.to_radians())
+            * (world_pos - translation).extend(0.0))
+        .truncate();
+        sprite_pos / self.scale * self.flip_sign() * Vec2::new(1.0, -1.0) + offset
+    }
+
+    /// Maps a point in the image to the sprite's space, before it is rotated and moved to its
+    /// translation.
+    fn image_to_sprite(&self, offset: Vec2, image_pos: Vec2) -> Vec2 {
+        (image_pos - offset) * Vec2::new(1.0, -1.0) * self.flip_sign() * self.scale
+    }
+
+    fn rotate(&self, v: Vec2) -> Vec2 {
+        (Quat::from_rotation_z(self.rotation.to_radians()) * v.extend(0.0)).truncate()
+    }
+
+    /// Sprite anchor placing the image point `offset` at the sprite's translation.
+    fn sprite_anchor(&self, offset: Vec2, image_size: Vec2) -> Anchor {
+        Anchor::Custom(
+            ((offset / image_size) - Vec2::new(0.5, 0.5)) * Vec2::new(1.0, -1.0) * self.flip_sign(),
+        )
+    }
+
+    /// Returns a copy of this frame using `image`, with offsets and hitboxes multiplied by `factor`.
+    fn scaled(&self, image: Handle<Image>, factor: f32) -> Self {
+        Self {
+            image,
+            offset: self.offset * factor,
+            root_motion: self.root_motion * factor,
+            hitboxes: Arc::new(
+                self.hitboxes
+                    .iter()
+                    .map(|(&id, hp)| {
+                        let hp = HitboxPos {
+                            pos: hp.pos * factor,
+                            size: hp.size * factor,
+                            ..hp.clone()
+                        };
+                        (id, hp)
+                    })
+                    .collect(),
+            ),
+            ..self.clone()
+        }
+    }
+
+    /// Where hitbox positions are measured from, relative to the sprite anchor.
+    fn hitbox_origin(&self, anchor_relative: bool) -> Vec2 {
+        if anchor_relative {
+            self.offset * Vec2::new(-1.0, 1.0)
+        } else {
+            Vec2::ZERO
+        }
+    }
+
     fn has_hitbox(&self, id: usize) -> bool {
         self.hitboxes.contains_key(&id)
     }
@@ -1050,7 +4410,7 @@ impl Frame {
     }
 
     fn hitbox_mut(&mut self, id: usize) -> &mut HitboxPos {
-        self.hitboxes.get_mut(&id).unwrap()
+        self.hitboxes_mut().get_mut(&id).unwrap()
     }
 
     fn get_hitbox(&self, id: usize) -> Option<&HitboxPos> {
@@ -1058,7 +4418,12 @@ impl Frame {
     }
 
     fn get_hitbox_mut(&mut self, id: usize) -> Option<&mut HitboxPos> {
-        self.hitboxes.get_mut(&id)
+        self.hitboxes_mut().get_mut(&id)
+    }
+
+    /// The hitbox positions, unshared from any undo history snapshots first.
+    fn hitboxes_mut(&mut self) -> &mut HashMap<usize, HitboxPos> {
+        Arc::make_mut(&mut self.hitboxes)
     }
 
     fn is_hitbox_enabled(&self, id: usize) -> bool {
@@ -1070,23 +4435,110 @@ struct Timeline {
     frames: Vec<Frame>,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(from = "HitboxData")]
 struct Hitbox {
     id: usize,
     desc: String,
+    kind: BoxKind,
+    /// Free-form category for game logic, such as "grab" or "projectile". The editor
+    /// doesn't interpret it.
+    tag: String,
+}
+
+/// A [`Hitbox`] as read from a file. Files from before box kinds only have `is_hurtbox`.
+#[derive(Deserialize)]
+struct HitboxData {
+    id: usize,
+    desc: String,
+    #[serde(default)]
+    kind: Option<BoxKind>,
+    #[serde(default)]
     is_hurtbox: bool,
+    #[serde(default)]
+    tag: String,
+}
+
+impl From<HitboxData> for Hitbox {
+    fn from(data: HitboxData) -> Self {
+        Hitbox {
+            id: data.id,
+            desc: data.desc,
+            kind: data.kind.unwrap_or(if data.is_hurtbox {
+                BoxKind::Hurt
+            } else {
+                BoxKind::Hit
+            }),
+            tag: data.tag,
+        }
+    }
+}
+
+/// What a box is for in game logic.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum BoxKind {
+    /// Deals damage.
+    #[default]
+    Hit,
+    /// Can be hit.
+    Hurt,
+    /// Keeps characters from overlapping, also known as a pushbox.
+    Collision,
+}
+
+impl BoxKind {
+    const ALL: [BoxKind; 3] = [BoxKind::Hit, BoxKind::Hurt, BoxKind::Collision];
+
+    fn label(self) -> &'static str {
+        match self {
+            BoxKind::Hit => "Hitbox",
+            BoxKind::Hurt => "Hurtbox",
+            BoxKind::Collision => "Collision box",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            BoxKind::Hit => Color::GREEN,
+            BoxKind::Hurt => Color::BLUE,
+            BoxKind::Collision => Color::ORANGE,
+        }
+    }
 }
 
-#[derive(PartialEq, Clone, Deserialize, Serialize)]
+#[derive(PartialEq, Clone, Deserialize, Serialize, JsonSchema)]
 struct HitboxPos {
     id: usize,
+    /// Top-left corner, y-up. Relative to the animation origin, or to the frame's `origin`
+    /// within the sprite when `Info::anchor_relative_hitboxes` is set.
+    #[schemars(with = "[f32; 2]")]
     pos: Vec2,
+    #[schemars(with = "[f32; 2]")]
     size: Vec2,
     enabled: bool,
 }
 
+impl HitboxPos {
+    /// Whether the box has a positive width and height; boxes without are invisible.
+    fn has_area(&self) -> bool {
+        self.size.x > 0.0 && self.size.y > 0.0
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::from_corners(self.pos, self.pos + self.size * Vec2::new(1.0, -1.0))
+    }
+}
+
+/// The cursor position last frame, or `None` if it was outside the window.
 #[derive(Resource)]
-struct LastMousePos(Vec2);
+struct LastMousePos(Option<Vec2>);
+/// How far the cursor moved since last frame, in logical pixels.
+///
+/// Everything on the canvas works in logical pixels: `cursor_position`,
+/// `viewport_to_world_2d` and the orthographic projection all divide out the window's
+/// scale factor, so a delta of one logical pixel is `proj.scale` world units on any display.
+/// Mixing in physical pixels anywhere would make drags too fast on HiDPI screens.
 #[derive(Resource)]
 struct MouseDelta(Vec2);
 
@@ -1097,11 +4549,107 @@ fn mouse_delta(
 ) {
     let Some(cur_mouse_pos) = primary_window.single().cursor_position() else {
         mouse_delta.0 = Vec2::splat(0.0);
+        last_mouse_pos.0 = None;
         return;
     };
 
-    mouse_delta.0 = cur_mouse_pos - last_mouse_pos.0;
-    last_mouse_pos.0 = cur_mouse_pos;
+    // Re-entering the window isn't movement, wherever the cursor left it.
+    mouse_delta.0 = last_mouse_pos
+        .0
+        .map_or(Vec2::splat(0.0), |last| cur_mouse_pos - last);
+    last_mouse_pos.0 = Some(cur_mouse_pos);
+}
+
+/// The file types picked through dialogs, so every dialog filters on and every chosen save
+/// path ends in the same extension.
+#[derive(Clone, Copy)]
+enum FileKind {
+    Anim,
+    Png,
+}
+
+impl FileKind {
+    fn extension(self) -> &'static str {
+        match self {
+            FileKind::Anim => "anim",
+            FileKind::Png => "png",
+        }
+    }
+
+    fn dialog(self) -> rfd::AsyncFileDialog {
+        rfd::AsyncFileDialog::new().add_filter(self.extension(), &[self.extension()])
+    }
+
+    /// Appends the extension to `path` unless it already ends in it, since not every
+    /// platform's dialog adds it for the user.
+    fn enforce_extension(self, path: &Path) -> PathBuf {
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(self.extension()))
+        {
+            return path.to_owned();
+        }
+        let mut path = path.as_os_str().to_owned();
+        path.push(".");
+        path.push(self.extension());
+        path.into()
+    }
+}
+
+/// Writes a file to a path picked through a save dialog.
+type PathWriter = Box<
+    dyn FnOnce(&mut EditorState, &Path, &Assets<Image>) -> Result<(), Box<dyn Error>> + Send + Sync,
+>;
+
+/// A file write to a path picked through a save dialog.
+struct PendingWrite {
+    path: PathBuf,
+    /// What the write is called in the message when it fails, e.g. "save".
+    verb: &'static str,
+    write: PathWriter,
+}
+
+impl PendingWrite {
+    /// Writes the file, reporting a failure as a toast, and releases the lock held since the
+    /// dialog opened.
+    fn run(self, editor_state: &mut EditorState, ui_state: &mut UiState, assets: &Assets<Image>) {
+        if let Err(err) = (self.write)(editor_state, &self.path, assets) {
+            ui_state.toast(format!(
+                "Could not {} {}: {err}",
+                self.verb,
+                self.path.display()
+            ));
+        }
+        editor_state.interaction_lock.release();
+    }
+}
+
+/// Writes to `picked` once it ends in `kind`'s extension. If appending the extension names an
+/// existing file, which the dialog never asked about replacing, the write waits for the user
+/// to confirm it.
+fn write_picked_path(
+    picked: &Path,
+    kind: FileKind,
+    verb: &'static str,
+    editor_state: &mut EditorState,
+    ui_state: &mut UiState,
+    assets: &Assets<Image>,
+    write: impl FnOnce(&mut EditorState, &Path, &Assets<Image>) -> Result<(), Box<dyn Error>>
+        + Send
+        + Sync
+        + 'static,
+) {
+    let path = kind.enforce_extension(picked);
+    let pending = PendingWrite {
+        verb,
+        write: Box::new(write),
+        path,
+    };
+    if pending.path != picked && pending.path.exists() {
+        ui_state.confirm_overwrite(pending);
+    } else {
+        pending.run(editor_state, ui_state, assets);
+    }
 }
 
 struct PendingFileDialog {
@@ -1112,10 +4660,32 @@ enum FileAction {
     LoadFrame(Pin<Box<dyn Future<Output = Option<Vec<FileHandle>>>>>),
     Save(Pin<Box<dyn Future<Output = Option<FileHandle>>>>),
     Open(Pin<Box<dyn Future<Output = Option<FileHandle>>>>),
+    ExportFrames(
+        RangeInclusive<usize>,
+        Pin<Box<dyn Future<Output = Option<FileHandle>>>>,
+    ),
+    ExportFramePng {
+        index: usize,
+        trimmed: bool,
+        dialog: Pin<Box<dyn Future<Output = Option<FileHandle>>>>,
+    },
+    ExportSequence(Pin<Box<dyn Future<Output = Option<FileHandle>>>>),
+    ExportBoxesByKind(Pin<Box<dyn Future<Output = Option<FileHandle>>>>),
+    ImportSequence(Pin<Box<dyn Future<Output = Option<FileHandle>>>>),
+    LocateImage {
+        index: usize,
+        dialog: Pin<Box<dyn Future<Output = Option<FileHandle>>>>,
+    },
+    LoadLightbox(Pin<Box<dyn Future<Output = Option<FileHandle>>>>),
+    SaveCanvasCapture(
+        image::RgbaImage,
+        Pin<Box<dyn Future<Output = Option<FileHandle>>>>,
+    ),
 }
 
 fn poll_pending_file_dialog(
     mut editor_state: ResMut<EditorState>,
+    mut ui_state: ResMut<UiState>,
     mut pending_file_dialog: NonSendMut<PendingFileDialog>,
     mut assets: ResMut<Assets<Image>>,
     mut commands: Commands,
@@ -1135,17 +4705,130 @@ fn poll_pending_file_dialog(
             }
             Poll::Ready(Some(val)) => {
                 pending_file_dialog.action = None;
-                for filename in val {
-                    let img =
-                        image::load_from_memory(&std::fs::read(filename.path()).unwrap()).unwrap();
-                    let handle = assets.add(Image::from_dynamic(img, true));
-                    let action = Action::AddFrame { image: handle };
-                    editor_state.do_action(action);
+                for filename in val {
+                    let img = match std::fs::read(filename.path())
+                        .map_err(|err| err.to_string())
+                        .and_then(|bytes| {
+                            image::load_from_memory(&bytes).map_err(|err| err.to_string())
+                        }) {
+                        Ok(img) => img,
+                        Err(err) => {
+                            ui_state.toast(format!(
+                                "Could not load {}: {err}",
+                                filename.path().display()
+                            ));
+                            continue;
+                        }
+                    };
+                    let img = editor_state.import_scale.apply(img);
+                    let handle = assets.add(Image::from_dynamic(img, true));
+                    let action = Action::AddFrame { image: handle };
+                    editor_state.do_action(action);
+                }
+                editor_state.interaction_lock.release();
+            }
+        },
+        FileAction::Save(fut) => match fut.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                // Cancelling the dialog cancels whatever was waiting on the save, such as
+                // exiting, so the user is back in the editor with nothing pending.
+                pending_file_dialog.action = None;
+                editor_state.action_after_save = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                pending_file_dialog.action = None;
+                write_picked_path(
+                    val.path(),
+                    FileKind::Anim,
+                    "save",
+                    &mut editor_state,
+                    &mut ui_state,
+                    &assets,
+                    |es, path, assets| es.save_to(path, assets),
+                );
+            }
+        },
+        FileAction::Open(fut) => match fut.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                pending_file_dialog.action = None;
+                let filename = val;
+                if let Err(err) = editor_state.load(filename.path(), &mut assets) {
+                    ui_state.toast(format!(
+                        "Could not open {}: {err}",
+                        filename.path().display()
+                    ));
+                }
+                editor_state.interaction_lock.release();
+            }
+        },
+        FileAction::ExportFrames(range, fut) => match fut.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                let range = range.clone();
+                pending_file_dialog.action = None;
+                write_picked_path(
+                    val.path(),
+                    FileKind::Anim,
+                    "export",
+                    &mut editor_state,
+                    &mut ui_state,
+                    &assets,
+                    move |es, path, assets| es.export_frames(range, path, assets),
+                );
+            }
+        },
+        FileAction::ExportFramePng {
+            index,
+            trimmed,
+            dialog,
+        } => match dialog.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                let (index, trimmed) = (*index, *trimmed);
+                pending_file_dialog.action = None;
+                write_picked_path(
+                    val.path(),
+                    FileKind::Png,
+                    "export",
+                    &mut editor_state,
+                    &mut ui_state,
+                    &assets,
+                    move |es, path, assets| es.export_frame_png(index, trimmed, path, assets),
+                );
+            }
+        },
+        FileAction::ExportSequence(fut) => match fut.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                pending_file_dialog.action = None;
+                if let Err(err) =
+                    write_sequence(val.path(), &editor_state.current_animation, &assets)
+                {
+                    ui_state.toast(format!("Could not export {}: {err}", val.path().display()));
                 }
                 editor_state.interaction_lock.release();
             }
         },
-        FileAction::Save(fut) => match fut.as_mut().poll(ctx) {
+        FileAction::ExportBoxesByKind(fut) => match fut.as_mut().poll(ctx) {
             Poll::Pending => {}
             Poll::Ready(None) => {
                 pending_file_dialog.action = None;
@@ -1153,12 +4836,13 @@ fn poll_pending_file_dialog(
             }
             Poll::Ready(Some(val)) => {
                 pending_file_dialog.action = None;
-                let filename = val;
-                editor_state.save_to(filename.path(), &assets);
+                if let Err(err) = write_boxes_by_kind(val.path(), &editor_state.current_animation) {
+                    ui_state.toast(format!("Could not export {}: {err}", val.path().display()));
+                }
                 editor_state.interaction_lock.release();
             }
         },
-        FileAction::Open(fut) => match fut.as_mut().poll(ctx) {
+        FileAction::ImportSequence(fut) => match fut.as_mut().poll(ctx) {
             Poll::Pending => {}
             Poll::Ready(None) => {
                 pending_file_dialog.action = None;
@@ -1166,26 +4850,119 @@ fn poll_pending_file_dialog(
             }
             Poll::Ready(Some(val)) => {
                 pending_file_dialog.action = None;
-                let filename = val;
-                editor_state.load(filename.path(), &mut assets);
+                match editor_state.import_sequence(val.path(), &mut assets) {
+                    Ok(missing) if !missing.is_empty() => {
+                        let frames = missing
+                            .iter()
+                            .map(|index| (index + 1).to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        ui_state.toast(format!(
+                            "Images for frames {frames} were missing and replaced with placeholders"
+                        ));
+                        ui_state.show_missing_images();
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        ui_state.toast(format!("Could not import {}: {err}", val.path().display()));
+                    }
+                }
+                editor_state.interaction_lock.release();
+            }
+        },
+        FileAction::LocateImage { index, dialog } => match dialog.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                let index = *index;
+                pending_file_dialog.action = None;
+                if let Err(err) = editor_state.locate_image(index, val.path(), &mut assets) {
+                    ui_state.toast(format!("Could not open {}: {err}", val.path().display()));
+                }
+                editor_state.interaction_lock.release();
+            }
+        },
+        FileAction::LoadLightbox(fut) => match fut.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
+                editor_state.interaction_lock.release();
+            }
+            Poll::Ready(Some(val)) => {
+                pending_file_dialog.action = None;
+                match image::open(val.path()) {
+                    Ok(img) => {
+                        let handle = assets.add(Image::from_dynamic(img, true));
+                        editor_state.lightbox.image = Some(handle);
+                    }
+                    Err(err) => {
+                        ui_state.toast(format!("Could not open {}: {err}", val.path().display()));
+                    }
+                }
+                editor_state.interaction_lock.release();
+            }
+        },
+        FileAction::SaveCanvasCapture(image, fut) => match fut.as_mut().poll(ctx) {
+            Poll::Pending => {}
+            Poll::Ready(None) => {
+                pending_file_dialog.action = None;
                 editor_state.interaction_lock.release();
             }
+            Poll::Ready(Some(val)) => {
+                let image = std::mem::take(image);
+                pending_file_dialog.action = None;
+                write_picked_path(
+                    val.path(),
+                    FileKind::Png,
+                    "save",
+                    &mut editor_state,
+                    &mut ui_state,
+                    &assets,
+                    move |_, path, _| Ok(image.save_with_format(path, ImageFormat::Png)?),
+                );
+            }
         },
     }
 }
 
-#[derive(Resource, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
 enum InteractionLock {
     None,
     Playback,
+    Modal,
     All,
 }
 
 impl InteractionLock {
+    /// Whether frames and hitboxes can be edited.
+    fn allows_canvas(&self) -> bool {
+        matches!(self, InteractionLock::None)
+    }
+
+    /// Whether the camera can be moved and the tool changed.
+    fn allows_navigation(&self) -> bool {
+        matches!(self, InteractionLock::None | InteractionLock::Playback)
+    }
+
+    fn allows_playback_toggle(&self) -> bool {
+        matches!(self, InteractionLock::None | InteractionLock::Playback)
+    }
+
+    fn allows_file_ops(&self) -> bool {
+        matches!(self, InteractionLock::None | InteractionLock::Playback)
+    }
+
     fn lock_all(&mut self) {
         *self = InteractionLock::All;
     }
 
+    fn lock_modal(&mut self) {
+        *self = InteractionLock::Modal;
+    }
+
     fn lock_playback(&mut self) {
         *self = InteractionLock::Playback;
     }
@@ -1195,22 +4972,67 @@ impl InteractionLock {
     }
 }
 
+/// A hitbox being resized with a shift-drag.
+struct HitboxResize {
+    /// The size the drag has reached, before the minimum size and snapping are applied.
+    unsnapped: Vec2,
+    pos: Vec2,
+    size: Vec2,
+}
+
+/// Grows a hitbox at `pos` by `growth`, in hitbox space. A centered resize grows both sides and
+/// keeps the center fixed; otherwise only the bottom-right corner moves, and is moved further by
+/// the offset `snap` gives for it. `unsnapped` is the size the drag reached so far, if any.
+fn resize_hitbox(
+    pos: Vec2,
+    size: Vec2,
+    unsnapped: Option<Vec2>,
+    growth: Vec2,
+    centered: bool,
+    snap: impl FnOnce(Vec2) -> Vec2,
+) -> HitboxResize {
+    let min_size = Vec2::splat(MIN_HITBOX_SIZE);
+    if centered {
+        let center = pos + size * Vec2::new(0.5, -0.5);
+        let unsnapped = unsnapped.unwrap_or(size) + growth * 2.0;
+        let size = unsnapped.max(min_size);
+        HitboxResize {
+            unsnapped,
+            pos: center + size * Vec2::new(-0.5, 0.5),
+            size,
+        }
+    } else {
+        let unsnapped = unsnapped.unwrap_or(size) + growth;
+        let corner = pos + unsnapped * Vec2::new(1.0, -1.0);
+        let snapped = unsnapped + snap(corner) * Vec2::new(1.0, -1.0);
+        HitboxResize {
+            unsnapped,
+            pos,
+            size: snapped.max(min_size),
+        }
+    }
+}
+
 fn mouse_interaction(
     delta: Res<MouseDelta>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     input: Query<&ActionState<Input2>>,
     mut editor_state: ResMut<EditorState>,
+    assets: Res<Assets<Image>>,
     mut query_camera: Query<
         (
             &mut Transform,
             &Camera,
             &GlobalTransform,
-            &OrthographicProjection,
+            &mut OrthographicProjection,
         ),
         With<Camera2d>,
     >,
+    mut wheel: EventReader<MouseWheel>,
+    mut contexts: EguiContexts,
 ) {
-    if editor_state.interaction_lock == InteractionLock::All {
+    editor_state.sampled_pixel = None;
+    if !editor_state.interaction_lock.allows_navigation() {
         return;
     }
 
@@ -1229,38 +5051,118 @@ fn mouse_interaction(
         camera.translation.y -= delta.y * proj.scale;
     }
 
-    if editor_state.interaction_lock >= InteractionLock::Playback {
+    let notches = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_NOTCH,
+        })
+        .sum::<f32>();
+    if notches != 0.0 && !contexts.ctx_mut().is_pointer_over_area() {
+        let scale =
+            (proj.scale * ZOOM_STEP.powf(-notches)).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        // Scale the camera's distance to the point under the cursor along with the zoom, so
+        // that point stays under the cursor.
+        if let Some(wp) = world_pos {
+            let translation = wp + (camera.translation.truncate() - wp) * (scale / proj.scale);
+            camera.translation.x = translation.x;
+            camera.translation.y = translation.y;
+        }
+        proj.scale = scale;
+    }
+
+    if !editor_state.interaction_lock.allows_canvas() {
         return;
     }
 
     if editor_state.get_frame(index).is_some() {
-        if input.just_pressed(Input2::LeftClick) {
+        if editor_state.selected_tool == Tool::Eyedropper
+            && let Some(wp) = world_pos
+        {
+            editor_state.sampled_pixel = editor_state.sample_pixel(wp, &assets);
+        }
+
+        let dragging = input.pressed(Input2::LeftClick) || input.pressed(Input2::ShiftLeftClick);
+        if input.just_pressed(Input2::CancelDrag) && dragging {
+            if let Some(from) = editor_state.drag_starting_pos.take() {
+                let resizing = input.pressed(Input2::ShiftLeftClick);
+                let resize_from = editor_state.resize_starting_pos.take();
+                match editor_state.selected_tool {
+                    Tool::Select => {
+                        if let Some(id) = editor_state.currently_selected_box {
+                            let hitbox = editor_state.frame_mut(index).hitbox_mut(id);
+                            if resizing {
+                                hitbox.size = from;
+                                if let Some(pos) = resize_from {
+                                    hitbox.pos = pos;
+                                }
+                            } else {
+                                hitbox.pos = from;
+                            }
+                        }
+                    }
+                    Tool::MoveAnchor => editor_state.frame_mut(index).offset = from,
+                    Tool::MoveRootMotion => editor_state.frame_mut(index).root_motion = from,
+                    _ => {}
+                }
+            }
+            editor_state.marquee = None;
+        }
+
+        let snap_threshold = editor_state
+            .snap_hitboxes
+            .then_some(HITBOX_SNAP_DISTANCE * proj.scale);
+
+        let over_egui = contexts.ctx_mut().is_pointer_over_area();
+        if input.just_pressed(Input2::ContextMenu)
+            && !dragging
+            && !over_egui
+            && let (Some(mp), Some(wp)) = (mouse_pos, world_pos)
+        {
+            let pos = editor_state.hitbox_space().from_world(wp);
+            let hitbox = if editor_state.show_hitboxes {
+                editor_state.hitbox_at(index, pos)
+            } else {
+                None
+            };
+            if let Some(id) = hitbox {
+                editor_state.currently_selected_box = Some(id);
+                if !editor_state.selected_boxes.contains(&id) {
+                    editor_state.selected_boxes = HashSet::from([id]);
+                }
+            }
+            let height = primary_window.single().height();
+            editor_state.canvas_menu = Some(CanvasMenu {
+                frame_index: index,
+                hitbox,
+                pos,
+                screen_pos: Vec2::new(mp.x, height - mp.y),
+            });
+        }
+
+        if input.just_pressed(Input2::LeftClick) && editor_state.canvas_menu.is_some() && over_egui
+        {
+            // The click is for the canvas menu, not the canvas below it.
+            editor_state.drag_starting_pos = None;
+            editor_state.marquee = None;
+        } else if input.just_pressed(Input2::LeftClick) {
+            editor_state.unsnapped_drag = None;
             match editor_state.selected_tool {
                 Tool::Select => {
                     if editor_state.show_hitboxes {
                         if let Some(wp) = world_pos {
-                            let mut selected = false;
-                            for hp in editor_state.frame(index).hitboxes.values() {
-                                if wp.x >= hp.pos.x
-                                    && wp.x <= hp.pos.x + hp.size.x
-                                    && wp.y <= hp.pos.y
-                                    && wp.y >= hp.pos.y - hp.size.y
-                                {
-                                    editor_state.currently_selected_box = Some(hp.id);
-                                    selected = true;
-                                    break;
-                                }
-                            }
+                            editor_state.currently_selected_box = editor_state
+                                .hitbox_at(index, editor_state.hitbox_space().from_world(wp));
 
-                            if !selected {
-                                editor_state.currently_selected_box = None;
+                            if let Some(id) = editor_state.currently_selected_box {
+                                if !editor_state.selected_boxes.contains(&id) {
+                                    editor_state.selected_boxes = HashSet::from([id]);
+                                }
+                                editor_state.drag_starting_pos =
+                                    Some(editor_state.frame(index).hitbox(id).pos);
                             } else {
-                                editor_state.drag_starting_pos = Some(
-                                    editor_state
-                                        .frame(index)
-                                        .hitbox(editor_state.currently_selected_box.unwrap())
-                                        .pos,
-                                );
+                                editor_state.selected_boxes.clear();
+                                editor_state.marquee = Some((wp, wp));
                             }
                         }
                     }
@@ -1271,23 +5173,50 @@ fn mouse_interaction(
                 Tool::MoveRootMotion => {
                     editor_state.drag_starting_pos = Some(editor_state.frame(index).root_motion);
                 }
-                Tool::CreateHitbox => {}
-                Tool::CreateHurtbox => {}
+                Tool::CreateHitbox | Tool::CreateHurtbox | Tool::CreateCollisionbox => {
+                    if let Some(wp) = world_pos {
+                        editor_state.marquee = Some((wp, wp));
+                    }
+                }
                 Tool::MoveSelected => {}
+                Tool::Eyedropper => {}
             }
         } else if input.pressed(Input2::LeftClick) {
             match editor_state.selected_tool {
                 Tool::Select => {
                     if editor_state.show_hitboxes {
                         if editor_state.drag_starting_pos.is_some() && let Some(id) = editor_state.currently_selected_box {
-                            editor_state.frame_mut(index).hitbox_mut(id).pos += delta * proj.scale;
+                            let flip = editor_state.frame(index).flip_sign();
+                            let scale = editor_state.hitbox_space().scale;
+                            let hitbox = editor_state.frame(index).hitbox(id);
+                            let size = hitbox.size;
+                            let pos = editor_state.unsnapped_drag.unwrap_or(hitbox.pos)
+                                + delta * proj.scale * flip / scale;
+                            editor_state.unsnapped_drag = Some(pos);
+                            let snap = snap_threshold.map_or(Vec2::ZERO, |threshold| {
+                                let rect = HitboxPos {
+                                    pos,
+                                    size,
+                                    id,
+                                    enabled: true,
+                                }
+                                .rect();
+                                editor_state.hitbox_snap(index, id, rect, threshold)
+                            });
+                            editor_state.frame_mut(index).hitbox_mut(id).pos = pos + snap;
+                        } else if let Some((start, _)) = editor_state.marquee
+                            && let Some(wp) = world_pos
+                        {
+                            editor_state.marquee = Some((start, wp));
                         }
                     }
                 }
                 Tool::MoveAnchor => {
                     if editor_state.drag_starting_pos.is_some() {
+                        let flip = editor_state.frame(index).flip_sign();
+                        let scale = editor_state.frame(index).scale;
                         editor_state.frame_mut(index).offset +=
-                            delta * proj.scale * Vec2::new(-1.0, 1.0);
+                            delta * proj.scale * Vec2::new(-1.0, 1.0) * flip / scale;
                     }
                 }
                 Tool::MoveRootMotion => {
@@ -1295,9 +5224,15 @@ fn mouse_interaction(
                         editor_state.frame_mut(index).root_motion += delta * proj.scale;
                     }
                 }
-                Tool::CreateHitbox => {}
-                Tool::CreateHurtbox => {}
+                Tool::CreateHitbox | Tool::CreateHurtbox | Tool::CreateCollisionbox => {
+                    if let Some((start, _)) = editor_state.marquee
+                        && let Some(wp) = world_pos
+                    {
+                        editor_state.marquee = Some((start, wp));
+                    }
+                }
                 Tool::MoveSelected => {}
+                Tool::Eyedropper => {}
             }
         } else if input.just_released(Input2::LeftClick) {
             match editor_state.selected_tool {
@@ -1311,8 +5246,25 @@ fn mouse_interaction(
                                 to: editor_state.frame(index).hitbox(id).pos.round(),
                             };
                             editor_state.do_action(action);
+                        } else if let Some((start, end)) = editor_state.marquee
+                            && start != end
+                        {
+                            let space = editor_state.hitbox_space();
+                            let marquee =
+                                Rect::from_corners(space.from_world(start), space.from_world(end));
+                            let mut selected = editor_state
+                                .frame(index)
+                                .hitboxes
+                                .values()
+                                .filter(|hp| hp.enabled && !hp.rect().intersect(marquee).is_empty())
+                                .map(|hp| hp.id)
+                                .collect::<Vec<_>>();
+                            selected.sort();
+                            editor_state.currently_selected_box = selected.first().copied();
+                            editor_state.selected_boxes = selected.into_iter().collect();
                         }
                     }
+                    editor_state.marquee = None;
                 }
                 Tool::MoveAnchor => {
                     if let Some(from) = editor_state.drag_starting_pos {
@@ -1334,38 +5286,43 @@ fn mouse_interaction(
                         editor_state.do_action(action);
                     }
                 }
-                Tool::CreateHitbox => {}
-                Tool::CreateHurtbox => {}
+                tool @ (Tool::CreateHitbox | Tool::CreateHurtbox | Tool::CreateCollisionbox) => {
+                    if let Some((start, end)) = editor_state.marquee.take() {
+                        let space = editor_state.hitbox_space();
+                        let rect = Rect::from_corners(
+                            space.from_world(start).round(),
+                            space.from_world(end).round(),
+                        );
+                        // A click without a drag draws nothing.
+                        if rect.width() >= MIN_HITBOX_SIZE && rect.height() >= MIN_HITBOX_SIZE {
+                            editor_state.create_hitbox_with_pos(
+                                index,
+                                Vec2::new(rect.min.x, rect.max.y),
+                                rect.size(),
+                                tool.box_kind().unwrap(),
+                            );
+                        }
+                    }
+                }
                 Tool::MoveSelected => {}
+                Tool::Eyedropper => {}
             }
         } else if input.just_pressed(Input2::ShiftLeftClick) {
             println!("{:?}", editor_state.selected_tool);
+            editor_state.unsnapped_drag = None;
             match editor_state.selected_tool {
                 Tool::Select => {
                     if editor_state.show_hitboxes {
                         if let Some(wp) = world_pos {
-                            let mut selected = false;
-                            for hp in editor_state.frame(index).hitboxes.values() {
-                                if wp.x >= hp.pos.x
-                                    && wp.x <= hp.pos.x + hp.size.x
-                                    && wp.y <= hp.pos.y
-                                    && wp.y >= hp.pos.y - hp.size.y
-                                {
-                                    editor_state.currently_selected_box = Some(hp.id);
-                                    selected = true;
-                                    break;
-                                }
-                            }
-
-                            if !selected {
-                                editor_state.currently_selected_box = None;
-                            } else {
-                                editor_state.drag_starting_pos = Some(
-                                    editor_state
-                                        .frame(index)
-                                        .hitbox(editor_state.currently_selected_box.unwrap())
-                                        .size,
-                                );
+                            editor_state.currently_selected_box = editor_state
+                                .hitbox_at(index, editor_state.hitbox_space().from_world(wp));
+
+                            if let Some(id) = editor_state.currently_selected_box {
+                                let hitbox = editor_state.frame(index).hitbox(id);
+                                let (size, pos) = (hitbox.size, hitbox.pos);
+                                editor_state.drag_starting_pos = Some(size);
+                                editor_state.resize_starting_pos =
+                                    editor_state.resize_from_center.then_some(pos);
                             }
                         }
                     }
@@ -1376,9 +5333,35 @@ fn mouse_interaction(
             match editor_state.selected_tool {
                 Tool::Select => {
                     if editor_state.show_hitboxes {
-                        if editor_state.drag_starting_pos.is_some() && let Some(id) = editor_state.currently_selected_box {
-                        editor_state.frame_mut(index).hitbox_mut(id).size += delta * proj.scale * Vec2::new(1.0, -1.0);
-                    }
+                        if editor_state.drag_starting_pos.is_some()
+                            && let Some(id) = editor_state.currently_selected_box
+                        {
+                            // A mirrored frame mirrors hitbox space, so the drag is mirrored
+                            // back to grow the box towards the cursor.
+                            let space = editor_state.hitbox_space();
+                            let growth = delta * proj.scale * Vec2::new(1.0, -1.0) * space.flip
+                                / space.scale;
+                            let hitbox = editor_state.frame(index).hitbox(id);
+                            let resize = resize_hitbox(
+                                hitbox.pos,
+                                hitbox.size,
+                                editor_state.unsnapped_drag,
+                                growth,
+                                editor_state.resize_starting_pos.is_some(),
+                                |corner| {
+                                    snap_threshold.map_or(Vec2::ZERO, |threshold| {
+                                        let rect = Rect::from_corners(corner, corner);
+                                        editor_state.hitbox_snap(index, id, rect, threshold)
+                                    })
+                                },
+                            );
+                            // The unclamped size is kept in `unsnapped_drag`, so that dragging
+                            // back past the minimum size feels continuous.
+                            editor_state.unsnapped_drag = Some(resize.unsnapped);
+                            let hitbox = editor_state.frame_mut(index).hitbox_mut(id);
+                            hitbox.pos = resize.pos;
+                            hitbox.size = resize.size;
+                        }
                     }
                 }
                 _ => {}
@@ -1387,15 +5370,31 @@ fn mouse_interaction(
             match editor_state.selected_tool {
                 Tool::Select => {
                     if editor_state.show_hitboxes {
-                        if let Some(from) = editor_state.drag_starting_pos && let Some(id) = editor_state.currently_selected_box {
-                        let action = Action::ResizeHitbox {
-                            frame_index: index,
-                            id,
-                            from,
-                            to: editor_state.frame(index).hitbox(id).size.round(),
-                        };
-                        editor_state.do_action(action);
-                    }
+                        if let Some(from) = editor_state.drag_starting_pos
+                            && let Some(id) = editor_state.currently_selected_box
+                        {
+                            let hitbox = editor_state.frame(index).hitbox(id);
+                            let (size, pos) = (hitbox.size, hitbox.pos);
+                            let resize = Action::ResizeHitbox {
+                                frame_index: index,
+                                id,
+                                from,
+                                to: size.round().max(Vec2::splat(MIN_HITBOX_SIZE)),
+                            };
+                            let action = match editor_state.resize_starting_pos.take() {
+                                Some(pos_from) => Action::Group(vec![
+                                    resize,
+                                    Action::MoveHitbox {
+                                        frame_index: index,
+                                        id,
+                                        from: pos_from,
+                                        to: pos.round(),
+                                    },
+                                ]),
+                                None => resize,
+                            };
+                            editor_state.do_action(action);
+                        }
                     }
                 }
                 _ => {}
@@ -1407,156 +5406,706 @@ fn mouse_interaction(
 #[derive(Component)]
 struct HitboxId(usize);
 
-fn keyboard_interaction(
-    input: Query<&ActionState<Input2>>,
+/// What a hitbox shape was last built with, so its path is only rebuilt when this changes.
+#[derive(Component, Clone, Copy, PartialEq)]
+struct HitboxLook {
+    rect: Rect,
+    z: f32,
+    color: Color,
+}
+
+/// World units per screen pixel of a fresh camera.
+const DEFAULT_ZOOM: f32 = 0.1;
+
+/// Factor the zoom changes by per mouse wheel notch.
+const ZOOM_STEP: f32 = 1.15;
+
+/// Range the mouse wheel can zoom within, in world units per screen pixel.
+const ZOOM_RANGE: RangeInclusive<f32> = 0.01..=2.0;
+
+/// Pixels of touchpad scrolling that count as one mouse wheel notch.
+const PIXELS_PER_NOTCH: f32 = 50.0;
+
+/// How much larger than the sprite and its hitboxes the view is when fitted to them.
+const FIT_VIEW_MARGIN: f32 = 1.2;
+
+/// Smallest width and height a resize allows, so boxes can't end up without area.
+const MIN_HITBOX_SIZE: f32 = 1.0;
+
+/// How close, in screen pixels, a dragged hitbox edge has to come to another one to snap to it.
+const HITBOX_SNAP_DISTANCE: f32 = 6.0;
+
+/// Commands that need more than the editor state to run, such as opening file dialogs, and
+/// anything else the command palette offers. They are queued on [`EditorState`] and run by
+/// [`run_editor_commands`].
+enum EditorCommand {
+    New,
+    Open,
+    OpenDialog,
+    Save,
+    SaveAs,
+    AddFrame,
+    ExportSelection,
+    ScaleAnimation(ImageScale),
+    PasteFrame,
+    ExportFramePng {
+        trimmed: bool,
+    },
+    /// Appends this many checkerboard frames, for blocking out timing before the art exists.
+    AddPlaceholderFrames(usize),
+    /// Inserts a checkerboard frame at this index.
+    InsertPlaceholderFrame(usize),
+    ExportSequence,
+    ExportBoxesByKind,
+    ImportSequence,
+    ImportSequenceDialog,
+    /// Asks where the missing image of this frame of the imported sequence went.
+    LocateImage(usize),
+    LoadLightbox,
+    OpenCommandPalette,
+    SelectTool(Tool),
+    DeleteFrame,
+    PrevFrame,
+    NextFrame,
+    Undo,
+    Redo,
+    TogglePlayback,
+    TogglePreview,
+    ToggleHitboxes,
+    ToggleGrid,
+    CaptureCanvas,
+    ApplyPivotMarkers,
+    SelectAllHitboxes,
+    DeselectAllHitboxes,
+    DuplicateFrame,
+    TogglePerformanceOverlay,
+    MirrorHitboxes(MirrorAxis),
+    InvertHitboxSelection,
+    SelectHitboxesByKind(BoxKind),
+    ToggleShortcuts,
+    FitView,
+    CenterHitboxOnAnchor,
+    SnapAnchorToHitbox,
+    CheckHitboxes,
+}
+
+impl EditorCommand {
+    /// Whether the command saves or exports the animation, so it should only run once
+    /// pending edits are committed.
+    fn writes_files(&self) -> bool {
+        matches!(
+            self,
+            EditorCommand::Save
+                | EditorCommand::SaveAs
+                | EditorCommand::ExportSelection
+                | EditorCommand::ExportFramePng { .. }
+                | EditorCommand::ExportSequence
+                | EditorCommand::ExportBoxesByKind
+        )
+    }
+}
+
+fn run_editor_commands(
     mut editor_state: ResMut<EditorState>,
     mut ui_state: ResMut<UiState>,
     mut pending_file_dialog: NonSendMut<PendingFileDialog>,
-    primary_window: Query<Entity, With<PrimaryWindow>>,
-    assets: Res<Assets<Image>>,
+    mut assets: ResMut<Assets<Image>>,
 ) {
-    if let Some(action) = editor_state.with_pfd.take() {
-        action(&mut pending_file_dialog);
+    while !editor_state.command_queue.is_empty() {
+        let commands = std::mem::take(&mut editor_state.command_queue);
+        for command in commands {
+            run_editor_command(
+                command,
+                &mut editor_state,
+                &mut ui_state,
+                &mut pending_file_dialog,
+                &mut assets,
+            );
+        }
     }
+}
 
-    if editor_state.interaction_lock == InteractionLock::All {
+fn run_editor_command(
+    command: EditorCommand,
+    editor_state: &mut EditorState,
+    ui_state: &mut UiState,
+    pending_file_dialog: &mut PendingFileDialog,
+    assets: &mut Assets<Image>,
+) {
+    if command.writes_files() && ui_state.text_field_focused() {
+        ui_state.run_after_edits_commit(command);
         return;
     }
 
+    match command {
+        EditorCommand::New => {
+            editor_state.confirm_if_unsaved(ui_state, EditorState::reset, true);
+        }
+        EditorCommand::Open => {
+            editor_state.confirm_if_unsaved(
+                ui_state,
+                |es| {
+                    es.interaction_lock.lock_all();
+                    es.command_queue.push(EditorCommand::OpenDialog);
+                },
+                false,
+            );
+        }
+        EditorCommand::OpenDialog => {
+            pending_file_dialog.action = Some(FileAction::Open(Box::pin(
+                FileKind::Anim.dialog().pick_file(),
+            )));
+        }
+        EditorCommand::Save => {
+            if let Err(err) = editor_state.save(pending_file_dialog, assets) {
+                ui_state.toast(format!("Could not save: {err}"));
+            }
+        }
+        EditorCommand::SaveAs => {
+            let future = editor_state.save_dialog().save_file();
+            editor_state.animation_running = false;
+            editor_state.frames_since_last_frame = 0;
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::Save(Box::pin(future)));
+        }
+        EditorCommand::AddFrame => {
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::LoadFrame(Box::pin(
+                rfd::AsyncFileDialog::new().pick_files(),
+            )));
+        }
+        EditorCommand::ExportSelection => {
+            let Some((start, end)) = editor_state.selected_frames else {
+                return;
+            };
+            let future = editor_state.save_dialog().save_file();
+            editor_state.animation_running = false;
+            editor_state.frames_since_last_frame = 0;
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action =
+                Some(FileAction::ExportFrames(start..=end, Box::pin(future)));
+        }
+        EditorCommand::ScaleAnimation(scale) => {
+            if let Err(err) = editor_state.scale_animation(scale, assets) {
+                ui_state.toast(format!("Could not scale the animation: {err}"));
+            }
+        }
+        EditorCommand::ExportFramePng { trimmed } => {
+            if editor_state.get_frame(editor_state.current_frame).is_none() {
+                return;
+            }
+            let future = FileKind::Png
+                .dialog()
+                .set_file_name(&format!("frame{}.png", editor_state.current_frame + 1))
+                .save_file();
+            editor_state.set_playback(false);
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::ExportFramePng {
+                index: editor_state.current_frame,
+                trimmed,
+                dialog: Box::pin(future),
+            });
+        }
+        EditorCommand::PasteFrame => {
+            let image = match arboard::Clipboard::new().and_then(|mut c| c.get_image()) {
+                Ok(image) => image,
+                Err(arboard::Error::ContentNotAvailable) => {
+                    ui_state.toast("The clipboard does not contain an image");
+                    return;
+                }
+                Err(err) => {
+                    ui_state.toast(format!("Could not read the clipboard: {err}"));
+                    return;
+                }
+            };
+            let Some(buffer) = image::RgbaImage::from_raw(
+                image.width as u32,
+                image.height as u32,
+                image.bytes.into_owned(),
+            ) else {
+                ui_state.toast("The clipboard image could not be decoded");
+                return;
+            };
+            let img = editor_state
+                .import_scale
+                .apply(DynamicImage::ImageRgba8(buffer));
+            let handle = assets.add(Image::from_dynamic(img, true));
+            editor_state.do_action(Action::AddFrame { image: handle });
+        }
+        EditorCommand::ExportSequence => {
+            let future = rfd::AsyncFileDialog::new().pick_folder();
+            editor_state.set_playback(false);
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::ExportSequence(Box::pin(future)));
+        }
+        EditorCommand::ExportBoxesByKind => {
+            let future = rfd::AsyncFileDialog::new().pick_folder();
+            editor_state.set_playback(false);
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::ExportBoxesByKind(Box::pin(future)));
+        }
+        EditorCommand::ImportSequence => {
+            editor_state.confirm_if_unsaved(
+                ui_state,
+                |es| {
+                    es.interaction_lock.lock_all();
+                    es.command_queue.push(EditorCommand::ImportSequenceDialog);
+                },
+                false,
+            );
+        }
+        EditorCommand::ImportSequenceDialog => {
+            pending_file_dialog.action = Some(FileAction::ImportSequence(Box::pin(
+                rfd::AsyncFileDialog::new().pick_folder(),
+            )));
+        }
+        EditorCommand::AddPlaceholderFrames(count) => {
+            let handle = assets.add(Image::from_dynamic(placeholder_image(), true));
+            let actions = (0..count)
+                .map(|_| Action::AddFrame {
+                    image: handle.clone(),
+                })
+                .collect();
+            editor_state.do_action(Action::Group(actions));
+        }
+        EditorCommand::InsertPlaceholderFrame(index) => {
+            let handle = assets.add(Image::from_dynamic(placeholder_image(), true));
+            let index = index.min(editor_state.current_animation.timeline.frames.len());
+            editor_state.do_action(Action::InsertFrame {
+                frame: Frame::new(handle),
+                index,
+            });
+        }
+        EditorCommand::OpenCommandPalette => {
+            editor_state.interaction_lock.lock_modal();
+            ui_state.open_command_palette();
+        }
+        EditorCommand::SelectTool(tool) => {
+            editor_state.selected_tool = tool;
+        }
+        EditorCommand::DeleteFrame => {
+            if let Some(frame) = editor_state.get_frame(editor_state.current_frame) {
+                let action = Action::RemoveFrame {
+                    frame: frame.clone(),
+                    index: editor_state.current_frame,
+                    tags: editor_state.current_animation.tags.clone(),
+                };
+                editor_state.do_action(action);
+            }
+        }
+        EditorCommand::PrevFrame => {
+            if editor_state.current_frame > 0 {
+                editor_state.current_frame -= 1;
+            }
+        }
+        EditorCommand::NextFrame => {
+            if editor_state.current_frame + 1 < editor_state.current_animation.timeline.frames.len()
+            {
+                editor_state.current_frame += 1;
+            }
+        }
+        EditorCommand::Undo => {
+            editor_state.undo();
+        }
+        EditorCommand::Redo => {
+            editor_state.redo();
+        }
+        EditorCommand::TogglePlayback => {
+            let running = !editor_state.animation_running;
+            editor_state.set_playback(running);
+        }
+        EditorCommand::TogglePreview => {
+            editor_state.toggle_preview_mode();
+        }
+        EditorCommand::ToggleHitboxes => {
+            editor_state.show_hitboxes = !editor_state.show_hitboxes;
+        }
+        EditorCommand::ToggleGrid => {
+            editor_state.show_grid = !editor_state.show_grid;
+        }
+        EditorCommand::FitView => {
+            editor_state.fit_view_requested = true;
+        }
+        EditorCommand::CaptureCanvas => {
+            editor_state.canvas_capture_requested = true;
+        }
+        EditorCommand::LocateImage(index) => {
+            let future = FileKind::Png.dialog().pick_file();
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::LocateImage {
+                index,
+                dialog: Box::pin(future),
+            });
+        }
+        EditorCommand::LoadLightbox => {
+            let future = rfd::AsyncFileDialog::new()
+                .add_filter("image", &["png", "jpg", "jpeg", "bmp", "gif"])
+                .pick_file();
+            editor_state.interaction_lock.lock_all();
+            pending_file_dialog.action = Some(FileAction::LoadLightbox(Box::pin(future)));
+        }
+        EditorCommand::TogglePerformanceOverlay => {
+            editor_state.show_performance = !editor_state.show_performance;
+        }
+        EditorCommand::DuplicateFrame => {
+            if editor_state.get_frame(editor_state.current_frame).is_some() {
+                let index = editor_state.current_frame;
+                editor_state.do_action(Action::DuplicateFrame { index });
+            }
+        }
+        EditorCommand::SelectAllHitboxes => {
+            editor_state.select_all_hitboxes();
+        }
+        EditorCommand::DeselectAllHitboxes => {
+            editor_state.deselect_all_hitboxes();
+        }
+        EditorCommand::MirrorHitboxes(axis) => {
+            editor_state.mirror_hitboxes(axis);
+        }
+        EditorCommand::CheckHitboxes => {
+            let issues = editor_state.check_hitboxes();
+            ui_state.show_hitbox_issues(issues);
+        }
+        EditorCommand::InvertHitboxSelection => {
+            editor_state.invert_hitbox_selection();
+        }
+        EditorCommand::SelectHitboxesByKind(kind) => {
+            editor_state.select_hitboxes_by_kind(kind);
+        }
+        EditorCommand::ToggleShortcuts => {
+            ui_state.toggle_shortcuts();
+        }
+        EditorCommand::CenterHitboxOnAnchor => {
+            if let Some(id) = editor_state.currently_selected_box {
+                let frame_index = editor_state.current_frame;
+                editor_state.center_hitbox_on_anchor(frame_index, id);
+            }
+        }
+        EditorCommand::SnapAnchorToHitbox => {
+            if let Some(id) = editor_state.currently_selected_box {
+                let frame_index = editor_state.current_frame;
+                editor_state.snap_anchor_to_hitbox(frame_index, id);
+            }
+        }
+        EditorCommand::ApplyPivotMarkers => {
+            let missing = editor_state.apply_pivot_markers(assets);
+            if !missing.is_empty() {
+                let frames = missing
+                    .iter()
+                    .map(|index| (index + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ui_state.toast(format!("No pivot marker found in frames {frames}"));
+            }
+        }
+    }
+}
+
+/// A grey checkerboard standing in for a frame's art.
+fn placeholder_image() -> DynamicImage {
+    const SIZE: u32 = 64;
+    const SQUARE: u32 = 8;
+    let image = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        if (x / SQUARE + y / SQUARE) % 2 == 0 {
+            image::Rgba([160, 160, 160, 255])
+        } else {
+            image::Rgba([96, 96, 96, 255])
+        }
+    });
+    DynamicImage::ImageRgba8(image)
+}
+
+fn keyboard_interaction(
+    input: Query<&ActionState<Input2>>,
+    mut editor_state: ResMut<EditorState>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    query_camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut contexts: EguiContexts,
+) {
     let input = input.single();
+    let egui_wants_keyboard = contexts.ctx_mut().wants_keyboard_input();
+    let lock = editor_state.interaction_lock;
 
-    if input.just_pressed(Input2::New) {
-        editor_state.confirm_if_unsaved(
-            &mut ui_state,
-            |es| {
-                es.current_animation = Animation::new();
-                es.current_frame = 0;
-                es.has_saved = true;
-                es.action_list = vec![];
-                es.undo_depth = 0;
-                es.action_after_save = None;
-                es.current_basepath = None;
-                es.currently_selected_box = None;
-                es.drag_starting_pos = None;
-            },
-            true,
-        );
+    if input.just_pressed(Input2::New) && lock.allows_file_ops() {
+        editor_state.command_queue.push(EditorCommand::New);
     }
-    if input.just_pressed(Input2::Open) {
-        editor_state.confirm_if_unsaved(
-            &mut ui_state,
-            |es| {
-                es.interaction_lock.lock_all();
-                es.with_pfd = Some(Box::new(|pfd: &mut PendingFileDialog| {
-                    pfd.action = Some(FileAction::Open(Box::pin(
-                        rfd::AsyncFileDialog::new().pick_file(),
-                    )));
-                }));
-            },
-            false,
-        );
+    if input.just_pressed(Input2::Open) && lock.allows_file_ops() {
+        editor_state.command_queue.push(EditorCommand::Open);
     }
-    if input.just_pressed(Input2::Save) {
-        editor_state.save(&mut pending_file_dialog, &assets);
+    if input.just_pressed(Input2::Save) && lock.allows_file_ops() {
+        editor_state.command_queue.push(EditorCommand::Save);
     }
-    if input.just_pressed(Input2::SaveAs) {
-        let future = rfd::AsyncFileDialog::new()
-            .add_filter("anim", &["anim"])
-            .save_file();
-        editor_state.animation_running = false;
-        editor_state.frames_since_last_frame = 0;
-        editor_state.interaction_lock.lock_all();
-        pending_file_dialog.action = Some(FileAction::Save(Box::pin(future)));
+    if input.just_pressed(Input2::SaveAs) && lock.allows_file_ops() {
+        editor_state.command_queue.push(EditorCommand::SaveAs);
+    }
+    if input.just_pressed(Input2::CommandPalette) && lock.allows_canvas() {
+        editor_state
+            .command_queue
+            .push(EditorCommand::OpenCommandPalette);
+    }
+    if input.just_pressed(Input2::ShowShortcuts) && !egui_wants_keyboard {
+        editor_state
+            .command_queue
+            .push(EditorCommand::ToggleShortcuts);
     }
-    if input.just_pressed(Input2::ToolSelect) {
+    if input.just_pressed(Input2::ToolSelect) && lock.allows_navigation() {
         editor_state.selected_tool = Tool::Select;
     }
-    if input.just_pressed(Input2::ToolMoveAnchor) {
+    if input.just_pressed(Input2::ToolMoveAnchor) && lock.allows_navigation() {
         editor_state.selected_tool = Tool::MoveAnchor;
     }
+    if input.just_pressed(Input2::ToolCreateHitbox) && lock.allows_navigation() {
+        editor_state.selected_tool = Tool::CreateHitbox;
+    }
+    if input.just_pressed(Input2::ToolCreateHurtbox) && lock.allows_navigation() {
+        editor_state.selected_tool = Tool::CreateHurtbox;
+    }
+    if input.just_pressed(Input2::ToolCreateCollisionbox) && lock.allows_navigation() {
+        editor_state.selected_tool = Tool::CreateCollisionbox;
+    }
 
-    if input.just_pressed(Input2::TogglePlayback) {
-        editor_state.animation_running = !editor_state.animation_running;
-        editor_state.frames_since_last_frame = 0;
-        if editor_state.animation_running {
-            editor_state.interaction_lock = InteractionLock::Playback;
-        } else {
-            editor_state.interaction_lock = InteractionLock::None;
-        }
+    if input.just_pressed(Input2::TogglePlayback) && lock.allows_playback_toggle() {
+        let running = !editor_state.animation_running;
+        editor_state.set_playback(running);
+    }
+    if input.just_pressed(Input2::TogglePreview)
+        && lock.allows_playback_toggle()
+        && !egui_wants_keyboard
+    {
+        editor_state.toggle_preview_mode();
     }
 
-    if editor_state.interaction_lock >= InteractionLock::Playback {
+    if !editor_state.interaction_lock.allows_canvas() {
         return;
     }
 
     if input.just_pressed(Input2::AddFrame) {
-        editor_state.interaction_lock.lock_all();
-        pending_file_dialog.action = Some(FileAction::LoadFrame(Box::pin(
-            rfd::AsyncFileDialog::new().pick_files(),
-        )));
+        editor_state.command_queue.push(EditorCommand::AddFrame);
+    }
+    if input.just_pressed(Input2::PasteFrame) {
+        editor_state.command_queue.push(EditorCommand::PasteFrame);
+    }
+    if input.just_pressed(Input2::DuplicateFrame)
+        && !egui_wants_keyboard
+        && editor_state.get_frame(editor_state.current_frame).is_some()
+    {
+        editor_state
+            .command_queue
+            .push(EditorCommand::DuplicateFrame);
     }
     if input.just_pressed(Input2::DeleteFrame) {
-        if let Some(frame) = editor_state.get_frame(editor_state.current_frame) {
-            let action = Action::RemoveFrame {
-                frame: frame.clone(),
-                index: editor_state.current_frame,
-            };
-            editor_state.do_action(action);
-        }
+        editor_state.command_queue.push(EditorCommand::DeleteFrame);
+    }
+    if input.just_pressed(Input2::FitView) && !egui_wants_keyboard {
+        editor_state.command_queue.push(EditorCommand::FitView);
     }
     if input.just_pressed(Input2::Undo) {
-        editor_state.undo();
+        editor_state.command_queue.push(EditorCommand::Undo);
     }
     if input.just_pressed(Input2::Redo) {
-        editor_state.redo();
+        editor_state.command_queue.push(EditorCommand::Redo);
     }
 
     if input.just_pressed(Input2::PrevFrame) {
-        if editor_state.current_frame > 0 {
-            editor_state.current_frame -= 1;
-        }
+        editor_state.command_queue.push(EditorCommand::PrevFrame);
     }
 
     if input.just_pressed(Input2::NextFrame) {
-        if editor_state.current_frame + 1 < editor_state.current_animation.timeline.frames.len() {
-            editor_state.current_frame += 1;
+        editor_state.command_queue.push(EditorCommand::NextFrame);
+    }
+
+    if input.just_pressed(Input2::ToggleSelectedHitbox)
+        && !egui_wants_keyboard
+        && editor_state.get_frame(editor_state.current_frame).is_some()
+    {
+        if let Some(id) = editor_state.currently_selected_box {
+            editor_state.toggle_hitbox(editor_state.current_frame, id);
+        }
+    }
+
+    if input.just_pressed(Input2::DeleteSelected)
+        && !egui_wants_keyboard
+        && lock.allows_canvas()
+        && editor_state.selected_tool == Tool::Select
+        && let Some(id) = editor_state.currently_selected_box
+        && editor_state
+            .get_frame(editor_state.current_frame)
+            .is_some_and(|frame| frame.is_hitbox_enabled(id))
+    {
+        let frame_index = editor_state.current_frame;
+        editor_state.do_action(Action::ToggleHitboxEnabled { frame_index, id });
+        editor_state.currently_selected_box = None;
+        editor_state.selected_boxes.remove(&id);
+    }
+
+    for (input_action, step) in [
+        (Input2::SelectPrevHitbox, -1),
+        (Input2::SelectNextHitbox, 1),
+    ] {
+        if input.just_pressed(input_action)
+            && !egui_wants_keyboard
+            && editor_state.get_frame(editor_state.current_frame).is_some()
+        {
+            editor_state.cycle_selected_hitbox(step);
+        }
+    }
+
+    if input.just_pressed(Input2::SelectAllHitboxes) && !egui_wants_keyboard {
+        editor_state
+            .command_queue
+            .push(EditorCommand::SelectAllHitboxes);
+    }
+    if input.just_pressed(Input2::DeselectAllHitboxes) && !egui_wants_keyboard {
+        editor_state
+            .command_queue
+            .push(EditorCommand::DeselectAllHitboxes);
+    }
+
+    if input.just_pressed(Input2::ToggleSelectedHurtbox) && !egui_wants_keyboard {
+        if let Some(id) = editor_state.currently_selected_box
+            && let Some(hitbox) = editor_state.current_animation.hitboxes.get(&id)
+        {
+            let from = hitbox.kind;
+            let to = if from == BoxKind::Hurt {
+                BoxKind::Hit
+            } else {
+                BoxKind::Hurt
+            };
+            editor_state.do_action(Action::SetHitboxKind { id, from, to });
+        }
+    }
+
+    if input.just_pressed(Input2::CreateHitboxAtCursor)
+        && !egui_wants_keyboard
+        && editor_state.get_frame(editor_state.current_frame).is_some()
+    {
+        let (camera, camera_transform) = query_camera.single();
+        let window = windows.single();
+        let cursor_pos = window.cursor_position();
+        let world_pos = cursor_pos.and_then(|mp| camera.viewport_to_world_2d(camera_transform, mp));
+
+        if let (Some(cp), Some(wp)) = (cursor_pos, world_pos) {
+            let size = editor_state.last_hitbox_size;
+            let pos =
+                (editor_state.hitbox_space().from_world(wp) + size * Vec2::new(-0.5, 0.5)).round();
+            let frame_index = editor_state.current_frame;
+            editor_state.begin_hitbox_creation(
+                frame_index,
+                pos,
+                size,
+                Some(Vec2::new(cp.x, window.height() - cp.y)),
+            );
         }
     }
 }
 
 fn render(
     mut editor_state: ResMut<EditorState>,
-    mut sprite_query: Query<(&mut Transform, &mut Handle<Image>, &mut Sprite)>,
-    mut marker_query: Query<&mut Transform, (With<MotionMarker>, Without<Sprite>)>,
+    mut sprite_query: Query<
+        (&mut Transform, &mut Handle<Image>, &mut Sprite),
+        (Without<ReferenceSprite>, Without<LightboxSprite>),
+    >,
+    mut marker_query: Query<
+        (&mut Transform, &mut Visibility),
+        (With<MotionMarker>, Without<Sprite>),
+    >,
     mut hitbox_shapes: Query<
         (
             Entity,
             &mut Transform,
             &mut bevy_prototype_lyon::prelude::Path,
-            &mut HitboxId,
+            &mut Fill,
+            &mut Visibility,
+            &mut HitboxLook,
+            &HitboxId,
         ),
         (Without<MotionMarker>, Without<Sprite>),
     >,
+    projection_query: Query<&OrthographicProjection, With<Camera2d>>,
     mut commands: Commands,
     assets: Res<Assets<Image>>,
 ) {
     let current_tool = editor_state.selected_tool;
+    let marker_scale = editor_state.marker_size * projection_query.single().scale;
     let current_frame = editor_state.current_frame;
     let always_show_root_motion = editor_state.always_show_root_motion;
+    let hitbox_space = editor_state.hitbox_space();
     let show_hitboxes = editor_state.show_hitboxes;
+    let in_preview = editor_state.preview_mode.is_some();
+    let selected_boxes = editor_state.selected_boxes.clone();
+    let kinds = editor_state
+        .current_animation
+        .hitboxes
+        .values()
+        .map(|hitbox| (hitbox.id, hitbox.kind))
+        .collect::<HashMap<_, _>>();
+    let hitbox_order = editor_state.current_animation.hitbox_order.clone();
+    let hitbox_z = |id: usize| {
+        let position = hitbox_order.iter().position(|&i| i == id).unwrap_or(0);
+        0.5 + position as f32 * 0.001
+    };
+    // Boxes without area are drawn as a small red square, so they can still be found.
+    let warning_extent = 4.0 * projection_query.single().scale;
+    let hitbox_look = |hp: &HitboxPos| {
+        let world_rect = hitbox_space.world_rect(hp);
+        let (rect, color) = if hp.has_area() {
+            let kind = kinds.get(&hp.id).copied().unwrap_or_default();
+            let color = hitbox_color(selected_boxes.contains(&hp.id), kind);
+            (world_rect, color)
+        } else {
+            let size = world_rect.size().max(Vec2::splat(warning_extent));
+            let rect = Rect::from_center_size(world_rect.center(), size);
+            (rect, Color::RED.with_a(0.6))
+        };
+        HitboxLook {
+            rect,
+            z: hitbox_z(hp.id),
+            color,
+        }
+    };
+    let build_shape = |rect: Rect| {
+        GeometryBuilder::build_as(&{
+            let mut shape = shapes::Rectangle::default();
+            shape.origin = RectangleOrigin::TopLeft;
+            shape.extents = rect.size();
+            shape
+        })
+    };
+    let hitbox_ids = editor_state
+        .current_animation
+        .hitboxes
+        .keys()
+        .copied()
+        .collect::<HashSet<_>>();
+    let [r, g, b, a] = editor_state.sprite_tint;
+    let tint = Color::rgba(r, g, b, a);
+    let (offset, root_motion) = editor_state.displayed_offsets().unwrap_or_default();
     let frame = editor_state
         .current_animation
         .timeline
         .frames
         .get_mut(current_frame);
-    let mut marker_transform = marker_query.single_mut();
+    let (mut marker_transform, mut marker_visibility) = marker_query.single_mut();
+    let marker_visible = if in_preview {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if *marker_visibility != marker_visible {
+        *marker_visibility = marker_visible;
+    }
+    marker_transform.scale = Vec3::new(marker_scale, marker_scale, 1.0);
     let (mut transform, mut img, mut sprite) = sprite_query.single_mut();
+    if sprite.color != tint {
+        sprite.color = tint;
+    }
     if let Some(frame) = frame {
         if current_tool == Tool::MoveRootMotion || always_show_root_motion {
-            transform.translation.x = frame.root_motion.x;
-            transform.translation.y = frame.root_motion.y;
-            marker_transform.translation.x = frame.root_motion.x;
-            marker_transform.translation.y = frame.root_motion.y;
+            transform.translation.x = root_motion.x;
+            transform.translation.y = root_motion.y;
+            marker_transform.translation.x = root_motion.x;
+            marker_transform.translation.y = root_motion.y;
         } else {
             transform.translation.x = 0.0;
             transform.translation.y = 0.0;
@@ -1564,25 +6113,38 @@ fn render(
             marker_transform.translation.y = 0.0;
         }
 
-        let mut drawn_hitboxes = vec![];
+        // Each hitbox keeps its shape entity while it exists, hidden when it isn't drawn, and
+        // only rebuilds its path when its look changes, since that re-tessellates the mesh.
+        let mut shaped_hitboxes = vec![];
 
-        for (e, mut hitbox_transform, mut shape, mut id) in hitbox_shapes.iter_mut() {
-            if let Some(hp) = frame.get_hitbox(id.0) && hp.enabled && show_hitboxes {
-                hitbox_transform.translation.x = hp.pos.x;
-                hitbox_transform.translation.y = hp.pos.y;
-                if current_tool == Tool::MoveRootMotion || always_show_root_motion {
-                    hitbox_transform.translation.x += frame.root_motion.x;
-                    hitbox_transform.translation.y += frame.root_motion.y;
-                }
-                *shape = GeometryBuilder::build_as(&{
-                    let mut rect = shapes::Rectangle::default();
-                    rect.origin = RectangleOrigin::TopLeft;
-                    rect.extents = hp.size;
-                    rect
-                });
-                drawn_hitboxes.push(id.0.clone());
-            } else {
+        for (e, mut hitbox_transform, mut shape, mut fill, mut visibility, mut look, id) in
+            hitbox_shapes.iter_mut()
+        {
+            if !hitbox_ids.contains(&id.0) {
                 commands.entity(e).despawn();
+                continue;
+            }
+            shaped_hitboxes.push(id.0);
+
+            let Some(hp) = frame
+                .get_hitbox(id.0)
+                .filter(|hp| hp.enabled && show_hitboxes)
+            else {
+                if *visibility != Visibility::Hidden {
+                    *visibility = Visibility::Hidden;
+                }
+                continue;
+            };
+            let new_look = hitbox_look(hp);
+            if *look != new_look {
+                hitbox_transform.translation =
+                    Vec3::new(new_look.rect.min.x, new_look.rect.max.y, new_look.z);
+                *shape = build_shape(new_look.rect);
+                *fill = Fill::color(new_look.color);
+                *look = new_look;
+            }
+            if *visibility != Visibility::Inherited {
+                *visibility = Visibility::Inherited;
             }
         }
 
@@ -1591,23 +6153,21 @@ fn render(
                 frame
                     .hitboxes
                     .values()
-                    .filter(|hp| hp.enabled && !drawn_hitboxes.contains(&hp.id))
+                    .filter(|hp| hp.enabled && !shaped_hitboxes.contains(&hp.id))
                     .map(|hp| {
+                        let look = hitbox_look(hp);
                         (
                             ShapeBundle {
-                                path: GeometryBuilder::build_as(&{
-                                    let mut rect = shapes::Rectangle::default();
-                                    rect.origin = RectangleOrigin::TopLeft;
-                                    rect.extents = hp.size;
-                                    rect
-                                }),
-                                transform: Transform {
-                                    translation: Vec3::new(hp.pos.x, hp.pos.y, 0.5),
-                                    ..default()
-                                },
+                                path: build_shape(look.rect),
+                                transform: Transform::from_xyz(
+                                    look.rect.min.x,
+                                    look.rect.max.y,
+                                    look.z,
+                                ),
                                 ..default()
                             },
-                            Fill::color(Color::GREEN.with_a(0.2)),
+                            Fill::color(look.color),
+                            look,
                             HitboxId(hp.id),
                         )
                     })
@@ -1616,11 +6176,12 @@ fn render(
         }
 
         if let Some(image) = assets.get(&img) {
-            let image_size = image.size();
-            sprite.anchor = Anchor::Custom(
-                ((frame.offset / image_size) - Vec2::new(0.5, 0.5)) * Vec2::new(1.0, -1.0),
-            );
+            sprite.anchor = frame.sprite_anchor(offset, image.size());
         }
+        sprite.flip_x = frame.flip_x;
+        sprite.flip_y = frame.flip_y;
+        transform.scale = frame.scale.extend(1.0);
+        transform.rotation = Quat::from_rotation_z(frame.rotation.to_radians());
         if *img != frame.image {
             *img = frame.image.clone();
         }
@@ -1628,6 +6189,558 @@ fn render(
         if *img != Handle::default() {
             *img = Handle::default();
         }
+        for (.., mut visibility, _, _) in hitbox_shapes.iter_mut() {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+        }
+    }
+}
+
+/// Applies a requested camera view, then mirrors the camera into [`EditorState`].
+fn sync_camera(
+    mut editor_state: ResMut<EditorState>,
+    mut query_camera: Query<(&mut Transform, &mut OrthographicProjection, &Camera), With<Camera2d>>,
+    sprite_query: Query<
+        (&GlobalTransform, &Sprite, &Handle<Image>),
+        (Without<ReferenceSprite>, Without<LightboxSprite>),
+    >,
+    assets: Res<Assets<Image>>,
+) {
+    let (mut transform, mut proj, camera) = query_camera.single_mut();
+    if editor_state.fit_view_requested {
+        editor_state.fit_view_requested = false;
+        let view = fit_view(&editor_state, camera, sprite_query.single(), &assets);
+        editor_state.pending_camera_view = Some(view);
+    }
+    if let Some((position, zoom)) = editor_state.pending_camera_view {
+        editor_state.pending_camera_view = None;
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        proj.scale = zoom;
+    }
+
+    let view = (transform.translation.truncate(), proj.scale);
+    if editor_state.camera_view != view {
+        editor_state.camera_view = view;
+    }
+}
+
+/// The camera position and zoom that show the current frame's sprite and enabled hitboxes,
+/// or the default view if there is no frame.
+fn fit_view(
+    editor_state: &EditorState,
+    camera: &Camera,
+    (sprite_transform, sprite, image): (&GlobalTransform, &Sprite, &Handle<Image>),
+    assets: &Assets<Image>,
+) -> (Vec2, f32) {
+    let default_view = (Vec2::ZERO, DEFAULT_ZOOM);
+    let Some(frame) = editor_state.get_frame(editor_state.current_frame) else {
+        return default_view;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return default_view;
+    };
+
+    let mut bounds = Rect {
+        min: Vec2::splat(f32::INFINITY),
+        max: Vec2::splat(f32::NEG_INFINITY),
+    };
+    if let Some(image) = assets.get(image) {
+        let size = sprite.custom_size.unwrap_or(image.size());
+        let corner = (Vec2::splat(-0.5) - sprite.anchor.as_vec()) * size;
+        for local in [
+            corner,
+            corner + size * Vec2::X,
+            corner + size * Vec2::Y,
+            corner + size,
+        ] {
+            bounds = bounds.union_point(
+                sprite_transform
+                    .transform_point(local.extend(0.0))
+                    .truncate(),
+            );
+        }
+    }
+    let space = editor_state.hitbox_space();
+    for hp in frame.hitboxes.values().filter(|hp| hp.enabled) {
+        bounds = bounds.union(space.world_rect(hp));
+    }
+
+    if bounds.min.cmpgt(bounds.max).any() {
+        return default_view;
+    }
+
+    let zoom = (bounds.size() / viewport_size).max_element() * FIT_VIEW_MARGIN;
+    (
+        bounds.center(),
+        zoom.clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end()),
+    )
+}
+
+/// Draws the canvas as currently shown, background, sprites and hitboxes but no editor UI, at
+/// the window's resolution and saves it as a PNG. Bevy 0.10 can't read the rendered frame
+/// back, so the scene is redrawn on the CPU.
+fn capture_canvas(
+    mut editor_state: ResMut<EditorState>,
+    mut pending_file_dialog: NonSendMut<PendingFileDialog>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    query_camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    sprites: Query<
+        (
+            &GlobalTransform,
+            &Sprite,
+            &Handle<Image>,
+            &ComputedVisibility,
+        ),
+        Without<LightboxSprite>,
+    >,
+    clear_color: Res<ClearColor>,
+    assets: Res<Assets<Image>>,
+) {
+    if !editor_state.canvas_capture_requested || pending_file_dialog.action.is_some() {
+        return;
+    }
+    editor_state.canvas_capture_requested = false;
+
+    let window = windows.single();
+    let (camera, camera_transform) = query_camera.single();
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+    let (width, height) = (window.physical_width(), window.physical_height());
+    let pixel_size = viewport_size / Vec2::new(width as f32, height as f32);
+
+    let mut layers = sprites
+        .iter()
+        .filter(|(.., visibility)| visibility.is_visible())
+        .filter_map(|(transform, sprite, handle, _)| {
+            let image = assets.get(handle)?.clone().try_into_dynamic().ok()?;
+            Some((transform, sprite, image.into_rgba8()))
+        })
+        .collect::<Vec<_>>();
+    layers.sort_by(|(a, ..), (b, ..)| a.translation().z.total_cmp(&b.translation().z));
+    let layers = layers
+        .into_iter()
+        .map(|(transform, sprite, image)| {
+            let size = sprite
+                .custom_size
+                .unwrap_or(Vec2::new(image.width() as f32, image.height() as f32));
+            (transform.affine().inverse(), sprite, size, image)
+        })
+        .collect::<Vec<_>>();
+
+    let mut boxes = vec![];
+    if editor_state.show_hitboxes
+        && let Some(frame) = editor_state.get_frame(editor_state.current_frame)
+    {
+        let space = editor_state.hitbox_space();
+        let animation = &editor_state.current_animation;
+        for id in &animation.hitbox_order {
+            if let Some(hp) = frame.get_hitbox(*id)
+                && hp.enabled
+            {
+                let kind = animation
+                    .hitboxes
+                    .get(id)
+                    .map_or(BoxKind::Hit, |hb| hb.kind);
+                let color = hitbox_color(editor_state.selected_boxes.contains(id), kind);
+                boxes.push((space.world_rect(hp), color.as_rgba_f32()));
+            }
+        }
+    }
+
+    let blend = |dst: &mut [f32; 4], src: [f32; 4]| {
+        for i in 0..3 {
+            dst[i] = src[i] * src[3] + dst[i] * (1.0 - src[3]);
+        }
+    };
+    let background = clear_color.0.as_rgba_f32();
+    let capture = image::RgbaImage::from_fn(width, height, |x, y| {
+        let mut color = background;
+        // Viewport coordinates start at the bottom left.
+        let viewport_pos = Vec2::new(x as f32 + 0.5, (height - y) as f32 - 0.5) * pixel_size;
+        let Some(world_pos) = camera.viewport_to_world_2d(camera_transform, viewport_pos) else {
+            return image::Rgba([0, 0, 0, 255]);
+        };
+
+        for (inverse, sprite, size, image) in &layers {
+            let local = inverse.transform_point3(world_pos.extend(0.0)).truncate();
+            let anchor = sprite.anchor.as_vec();
+            let mut uv = local / *size + Vec2::splat(0.5) + anchor;
+            uv.y = 1.0 - uv.y;
+            if sprite.flip_x {
+                uv.x = 1.0 - uv.x;
+            }
+            if sprite.flip_y {
+                uv.y = 1.0 - uv.y;
+            }
+            if uv.cmplt(Vec2::ZERO).any() || uv.cmpge(Vec2::ONE).any() {
+                continue;
+            }
+            let pixel = image.get_pixel(
+                (uv.x * image.width() as f32) as u32,
+                (uv.y * image.height() as f32) as u32,
+            );
+            let tint = sprite.color.as_rgba_f32();
+            let mut src = [0.0; 4];
+            for i in 0..4 {
+                src[i] = pixel[i] as f32 / 255.0 * tint[i];
+            }
+            blend(&mut color, src);
+        }
+
+        for (rect, box_color) in &boxes {
+            if rect.contains(world_pos) {
+                blend(&mut color, *box_color);
+            }
+        }
+
+        let [r, g, b, _] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        image::Rgba([r, g, b, 255])
+    });
+
+    let future = FileKind::Png
+        .dialog()
+        .set_file_name("canvas.png")
+        .save_file();
+    editor_state.interaction_lock.lock_all();
+    pending_file_dialog.action = Some(FileAction::SaveCanvasCapture(capture, Box::pin(future)));
+}
+
+fn render_lightbox(
+    editor_state: Res<EditorState>,
+    mut lightbox_query: Query<
+        (
+            &mut Transform,
+            &mut Handle<Image>,
+            &mut Sprite,
+            &mut Visibility,
+        ),
+        With<LightboxSprite>,
+    >,
+) {
+    let (mut transform, mut img, mut sprite, mut visibility) = lightbox_query.single_mut();
+    let lightbox = &editor_state.lightbox;
+    let Some(image) = lightbox
+        .image
+        .as_ref()
+        .filter(|_| editor_state.preview_mode.is_none())
+    else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    if *visibility != Visibility::Visible {
+        *visibility = Visibility::Visible;
+    }
+    if *img != *image {
+        *img = image.clone();
+    }
+    transform.translation.x = lightbox.position.x;
+    transform.translation.y = lightbox.position.y;
+    transform.scale = Vec3::new(lightbox.scale, lightbox.scale, 1.0);
+    sprite.color = Color::WHITE.with_a(lightbox.opacity);
+}
+
+fn render_reference_frame(
+    editor_state: Res<EditorState>,
+    mut reference_query: Query<
+        (
+            &mut Transform,
+            &mut Handle<Image>,
+            &mut Sprite,
+            &mut Visibility,
+        ),
+        With<ReferenceSprite>,
+    >,
+    assets: Res<Assets<Image>>,
+) {
+    let (mut transform, mut img, mut sprite, mut visibility) = reference_query.single_mut();
+    let reference = editor_state
+        .reference_frame
+        .filter(|_| editor_state.preview_mode.is_none())
+        .and_then(|index| editor_state.get_frame(index));
+    let Some(frame) = reference else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+
+    let root_motion = if editor_state.root_motion_visible() {
+        frame.root_motion
+    } else {
+        Vec2::ZERO
+    };
+    transform.translation.x = root_motion.x;
+    transform.translation.y = root_motion.y;
+
+    if let Some(image) = assets.get(&frame.image) {
+        sprite.anchor = frame.sprite_anchor(frame.offset, image.size());
+    }
+    sprite.flip_x = frame.flip_x;
+    sprite.flip_y = frame.flip_y;
+    transform.scale = frame.scale.extend(1.0);
+    transform.rotation = Quat::from_rotation_z(frame.rotation.to_radians());
+    if *img != frame.image {
+        *img = frame.image.clone();
+    }
+}
+
+fn hitbox_color(selected: bool, kind: BoxKind) -> Color {
+    if selected {
+        Color::YELLOW.with_a(0.3)
+    } else {
+        kind.color().with_a(0.2)
+    }
+}
+
+fn render_marquee(
+    editor_state: Res<EditorState>,
+    mut marquee_query: Query<
+        (
+            &mut Transform,
+            &mut bevy_prototype_lyon::prelude::Path,
+            &mut Visibility,
+        ),
+        With<Marquee>,
+    >,
+) {
+    let (mut transform, mut shape, mut visibility) = marquee_query.single_mut();
+    let Some((start, end)) = editor_state.marquee else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let rect = Rect::from_corners(start, end);
+    transform.translation.x = rect.min.x;
+    transform.translation.y = rect.max.y;
+    *shape = GeometryBuilder::build_as(&{
+        let mut marquee = shapes::Rectangle::default();
+        marquee.origin = RectangleOrigin::TopLeft;
+        marquee.extents = rect.size();
+        marquee
+    });
+    *visibility = Visibility::Visible;
+}
+
+/// Outlines the cell the current frame will be saved into, and the trimmed content within
+/// it, mirroring the trim and padding done by [`write_animation`].
+fn update_trim_preview(
+    mut editor_state: ResMut<EditorState>,
+    assets: Res<Assets<Image>>,
+    mut bounds_cache: Local<HashMap<(HandleId, u8), Option<(u32, u32, u32, u32)>>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    sprite_query: Query<
+        &Transform,
+        (
+            With<Sprite>,
+            Without<ReferenceSprite>,
+            Without<LightboxSprite>,
+        ),
+    >,
+    mut cell_query: Query<
+        (
+            &mut Transform,
+            &mut bevy_prototype_lyon::prelude::Path,
+            &mut Visibility,
+        ),
+        (With<TrimPreviewOutline>, Without<Sprite>),
+    >,
+    mut content_query: Query<
+        (
+            &mut Transform,
+            &mut bevy_prototype_lyon::prelude::Path,
+            &mut Visibility,
+        ),
+        (
+            With<TrimContentOutline>,
+            Without<TrimPreviewOutline>,
+            Without<Sprite>,
+        ),
+    >,
+) {
+    for event in image_events.iter() {
+        // Images are replaced in place when a missing one is located.
+        if let AssetEvent::Modified { handle } = event {
+            bounds_cache.retain(|(id, _), _| *id != handle.id());
+        }
+    }
+
+    let (mut cell_transform, mut cell_shape, mut cell_visibility) = cell_query.single_mut();
+    let (mut content_transform, mut content_shape, mut content_visibility) =
+        content_query.single_mut();
+    editor_state.trim_preview = None;
+    *cell_visibility = Visibility::Hidden;
+    *content_visibility = Visibility::Hidden;
+
+    let show_outlines = editor_state.show_trim_preview || editor_state.show_saved_cell;
+    if !show_outlines && !editor_state.show_trim_comparison {
+        return;
+    }
+
+    let mut bounds = vec![];
+    for frame in &editor_state.current_animation.timeline.frames {
+        let key = (
+            frame.image.id(),
+            editor_state.export_settings.alpha_threshold,
+        );
+        if !bounds_cache.contains_key(&key) {
+            let Some(image) = assets.get(&frame.image) else {
+                return;
+            };
+            // Images that can't be converted are left out, as they can't be saved either.
+            let image_bounds = image
+                .clone()
+                .try_into_dynamic()
+                .ok()
+                .map(|image| content_bounds(&image, key.1));
+            bounds_cache.insert(key, image_bounds);
+        }
+        bounds.push(bounds_cache[&key]);
+    }
+
+    let Some(frame) = editor_state.get_frame(editor_state.current_frame) else {
+        return;
+    };
+    let Some((left, top, width, height)) = bounds[editor_state.current_frame] else {
+        return;
+    };
+    let (cell_width, cell_height) = if editor_state.export_settings.tight_packing {
+        (width, height)
+    } else {
+        (
+            bounds.iter().flatten().map(|b| b.2).max().unwrap_or(0),
+            bounds.iter().flatten().map(|b| b.3).max().unwrap_or(0),
+        )
+    };
+    let pad_left = (cell_width - width) / 2;
+    let pad_top = (cell_height - height) / 2;
+    let pad_right = cell_width - width - pad_left;
+    let pad_bottom = cell_height - height - pad_top;
+
+    let cell_origin = Vec2::new(left as f32 - pad_left as f32, top as f32 - pad_top as f32);
+    let offset = frame.offset;
+    let anchor = sprite_query.single().translation.truncate();
+    // The outlines are drawn unrotated in the sprite's space, and turned with it.
+    let sprite_rect = |min: Vec2, width: u32, height: u32| {
+        let max = min + Vec2::new(width as f32, height as f32);
+        Rect::from_corners(
+            frame.image_to_sprite(offset, min),
+            frame.image_to_sprite(offset, max),
+        )
+    };
+    let rotation = Quat::from_rotation_z(frame.rotation.to_radians());
+    let place = |transform: &mut Transform, rect: Rect| {
+        let top_left = anchor + frame.rotate(Vec2::new(rect.min.x, rect.max.y));
+        transform.translation.x = top_left.x;
+        transform.translation.y = top_left.y;
+        transform.rotation = rotation;
+    };
+
+    let outline = |rect: Rect| {
+        GeometryBuilder::build_as(&{
+            let mut outline = shapes::Rectangle::default();
+            outline.origin = RectangleOrigin::TopLeft;
+            outline.extents = rect.size();
+            outline
+        })
+    };
+
+    let cell_rect = sprite_rect(cell_origin, cell_width, cell_height);
+    place(&mut cell_transform, cell_rect);
+    *cell_shape = outline(cell_rect);
+    if show_outlines {
+        *cell_visibility = Visibility::Visible;
+    }
+
+    if editor_state.show_saved_cell {
+        let content_rect = sprite_rect(Vec2::new(left as f32, top as f32), width, height);
+        place(&mut content_transform, content_rect);
+        *content_shape = outline(content_rect);
+        *content_visibility = Visibility::Visible;
+    }
+
+    editor_state.trim_preview = Some(TrimPreview {
+        offset: offset - cell_origin,
+        padding: [pad_left, pad_top, pad_right, pad_bottom],
+        content: [left, top, width, height],
+    });
+}
+
+fn update_grid(
+    editor_state: Res<EditorState>,
+    sprite_query: Query<
+        &Transform,
+        (
+            With<Sprite>,
+            Without<ReferenceSprite>,
+            Without<LightboxSprite>,
+        ),
+    >,
+    mut grid_query: Query<(&mut Transform, &mut Visibility), (With<GridLine>, Without<Sprite>)>,
+) {
+    let origin = if editor_state.grid_at_anchor {
+        sprite_query.single().translation.truncate()
+    } else {
+        Vec2::ZERO
+    };
+
+    let grid_visibility = if editor_state.show_grid {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for (mut transform, mut visibility) in grid_query.iter_mut() {
+        transform.translation.x = origin.x;
+        transform.translation.y = origin.y;
+        if *visibility != grid_visibility {
+            *visibility = grid_visibility;
+        }
+    }
+}
+
+fn update_window_title(
+    editor_state: Res<EditorState>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let name = &editor_state.current_animation.metadata.name;
+    let title = if name.is_empty() {
+        "fg-anim-editor".to_string()
+    } else {
+        format!("{name} - fg-anim-editor")
+    };
+
+    let mut window = windows.single_mut();
+    if window.title != title {
+        window.title = title;
+    }
+}
+
+/// Only measures frame times while the performance overlay is shown.
+fn sync_performance_diagnostics(
+    editor_state: Res<EditorState>,
+    mut diagnostics: ResMut<Diagnostics>,
+) {
+    for id in [
+        FrameTimeDiagnosticsPlugin::FPS,
+        FrameTimeDiagnosticsPlugin::FRAME_TIME,
+    ] {
+        if let Some(diagnostic) = diagnostics.get_mut(id)
+            && diagnostic.is_enabled != editor_state.show_performance
+        {
+            diagnostic.is_enabled = editor_state.show_performance;
+        }
+    }
+}
+
+/// Ticks the animator at the animation's authoring frame rate.
+fn sync_playback_rate(editor_state: Res<EditorState>, mut fixed_time: ResMut<FixedTime>) {
+    let period = editor_state.current_animation.tick_duration();
+    if fixed_time.period != period {
+        fixed_time.period = period;
     }
 }
 
@@ -1650,7 +6763,7 @@ fn animator(mut editor_state: ResMut<EditorState>) {
 
     let frame = editor_state.frame(index);
 
-    if editor_state.frames_since_last_frame >= frame.delay {
+    if editor_state.frames_since_last_frame >= frame.duration() {
         let mut new_index = index + 1;
         if new_index >= editor_state.current_animation.timeline.frames.len() {
             new_index = 0;
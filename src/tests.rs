@@ -0,0 +1,260 @@
+use super::*;
+
+fn state_with_frames(count: usize) -> EditorState {
+    let mut state = EditorState::new(true);
+    for _ in 0..count {
+        state.do_action(Action::AddFrame {
+            image: Handle::default(),
+        });
+    }
+    state
+}
+
+fn assert_current_frame_in_range(state: &EditorState) {
+    let frame_count = state.current_animation.timeline.frames.len();
+    assert!(
+        state.current_frame < frame_count.max(1),
+        "current frame {} with {frame_count} frames",
+        state.current_frame
+    );
+}
+
+fn remove_frame(state: &EditorState, index: usize) -> Action {
+    Action::RemoveFrame {
+        frame: state.frame(index).clone(),
+        index,
+        tags: state.current_animation.tags.clone(),
+    }
+}
+
+#[test]
+fn undoing_and_redoing_frame_removal_keeps_current_frame_in_range() {
+    let mut state = state_with_frames(3);
+    state.current_frame = 2;
+
+    state.do_action(remove_frame(&state, 2));
+    assert_current_frame_in_range(&state);
+    state.do_action(remove_frame(&state, 1));
+    assert_current_frame_in_range(&state);
+
+    state.undo();
+    assert_current_frame_in_range(&state);
+    state.undo();
+    assert_current_frame_in_range(&state);
+    state.redo();
+    assert_current_frame_in_range(&state);
+    state.redo();
+    assert_current_frame_in_range(&state);
+    assert_eq!(state.current_frame, 0);
+}
+
+#[test]
+fn undoing_frame_additions_keeps_current_frame_in_range() {
+    let mut state = state_with_frames(4);
+    state.current_frame = 3;
+
+    for _ in 0..4 {
+        state.undo();
+        assert_current_frame_in_range(&state);
+    }
+    assert_eq!(state.current_frame, 0);
+
+    for _ in 0..4 {
+        state.redo();
+        assert_current_frame_in_range(&state);
+    }
+}
+
+#[test]
+fn undoing_duplicate_and_insert_keeps_current_frame_in_range() {
+    let mut state = state_with_frames(2);
+
+    state.do_action(Action::DuplicateFrame { index: 1 });
+    assert_eq!(state.current_frame, 2);
+    state.do_action(Action::InsertFrame {
+        frame: Frame::new(Handle::default()),
+        index: 3,
+    });
+    assert_eq!(state.current_frame, 3);
+
+    for _ in 0..4 {
+        state.undo();
+        assert_current_frame_in_range(&state);
+    }
+    for _ in 0..4 {
+        state.redo();
+        assert_current_frame_in_range(&state);
+    }
+    assert_eq!(state.current_animation.timeline.frames.len(), 4);
+}
+
+#[test]
+fn clearing_frames_and_undoing_keeps_current_frame_in_range() {
+    let mut state = state_with_frames(3);
+    state.current_frame = 2;
+
+    state.clear_frames(true);
+    assert_current_frame_in_range(&state);
+    state.undo();
+    assert_current_frame_in_range(&state);
+    assert_eq!(state.current_animation.timeline.frames.len(), 3);
+    state.redo();
+    assert_current_frame_in_range(&state);
+}
+
+fn frame_with_hitboxes(count: usize) -> Frame {
+    let mut frame = Frame::new(Handle::default());
+    frame.hitboxes_mut().extend((0..count).map(|id| {
+        (
+            id,
+            HitboxPos {
+                id,
+                pos: Vec2::new(id as f32, 0.0),
+                size: Vec2::ONE,
+                enabled: true,
+            },
+        )
+    }));
+    frame
+}
+
+#[test]
+fn frame_snapshots_share_hitboxes_until_edited() {
+    let mut state = EditorState::new(true);
+    state.current_animation.timeline.frames = (0..3).map(|_| frame_with_hitboxes(4)).collect();
+
+    state.clear_frames(true);
+    state.undo();
+    let Some(Action::Group(actions)) = state.action_list.last() else {
+        panic!("clearing frames is not a group");
+    };
+    let Action::RemoveFrame { frame, index, .. } = &actions[0] else {
+        panic!("clearing frames does not start with a removal");
+    };
+    let restored = state.frame(*index);
+    assert!(Arc::ptr_eq(&frame.hitboxes, &restored.hitboxes));
+
+    let mut edited = restored.clone();
+    edited.hitbox_mut(0).enabled = false;
+    assert!(!Arc::ptr_eq(&frame.hitboxes, &edited.hitboxes));
+    assert!(frame.hitbox(0).enabled);
+}
+
+#[test]
+fn content_bounds_trims_fringe_pixels_at_or_below_the_threshold() {
+    // An opaque 4x3 block at (3, 2), inside a one pixel fringe of faint pixels.
+    let mut image = DynamicImage::new_rgba8(10, 8);
+    let pixels = image.as_mut_rgba8().unwrap();
+    for x in 2..8 {
+        for y in 1..6 {
+            pixels[(x, y)].0 = [255, 255, 255, 16];
+        }
+    }
+    for x in 3..7 {
+        for y in 2..5 {
+            pixels[(x, y)].0 = [255, 255, 255, 255];
+        }
+    }
+
+    assert_eq!(content_bounds(&image, 16), (3, 2, 4, 3));
+    assert_eq!(content_bounds(&image, 15), (2, 1, 6, 5));
+    assert_eq!(content_bounds(&image, 255), (10, 8, 0, 0));
+}
+
+#[test]
+fn cancelling_the_save_prompt_drops_the_pending_exit() {
+    let mut state = state_with_frames(1);
+    let mut ui_state = UiState::default();
+    assert!(!state.has_saved);
+
+    state.confirm_if_unsaved(&mut ui_state, |es| es.exit_now = true, true);
+    assert!(ui_state.show_save_menu);
+    assert!(state.action_after_save.is_some());
+
+    ui_state.cancel_save_menu(&mut state);
+    assert!(!ui_state.show_save_menu);
+    assert!(state.action_after_save.is_none());
+    assert!(!state.exit_now);
+    assert!(state.interaction_lock.allows_canvas());
+}
+
+#[test]
+fn collapsing_held_frames_keeps_the_total_duration() {
+    let mut state = state_with_frames(5);
+    let animation = &mut state.current_animation;
+    for (frame, (delay, repeat)) in
+        animation
+            .timeline
+            .frames
+            .iter_mut()
+            .zip([(2, 1), (3, 2), (1, 0), (4, 1), (5, 1)])
+    {
+        frame.delay = delay;
+        frame.repeat = repeat;
+    }
+    animation.tags = vec![
+        FrameTag {
+            name: "held".into(),
+            color: [255, 0, 0],
+            start: 3,
+            end: 9,
+        },
+        FrameTag {
+            name: "past the end".into(),
+            color: [0, 255, 0],
+            start: 7,
+            end: 8,
+        },
+    ];
+
+    let total = |animation: &Animation| -> usize {
+        animation.timeline.frames.iter().map(Frame::duration).sum()
+    };
+    let collapsed = animation.collapse_held_frames();
+    assert_eq!(total(&collapsed), total(animation));
+    assert_eq!(collapsed.timeline.frames.len(), 2);
+    assert_eq!(collapsed.tags.len(), 1);
+    assert_eq!((collapsed.tags[0].start, collapsed.tags[0].end), (1, 1));
+}
+
+#[test]
+fn resizing_a_hitbox_past_its_size_keeps_it_positive() {
+    let pos = Vec2::new(10.0, 20.0);
+    let size = Vec2::new(8.0, 6.0);
+    let growth = Vec2::new(-100.0, -50.0);
+
+    let corner = resize_hitbox(pos, size, None, growth, false, |_| Vec2::ZERO);
+    assert_eq!(corner.unsnapped, size + growth);
+    assert_eq!(corner.pos, pos);
+    assert_eq!(corner.size, Vec2::splat(MIN_HITBOX_SIZE));
+
+    let centered = resize_hitbox(pos, size, None, growth, true, |_| Vec2::ZERO);
+    assert_eq!(centered.unsnapped, size + growth * 2.0);
+    assert_eq!(centered.size, Vec2::splat(MIN_HITBOX_SIZE));
+    let center = pos + size * Vec2::new(0.5, -0.5);
+    assert_eq!(centered.pos + centered.size * Vec2::new(0.5, -0.5), center);
+
+    // Dragging back continues from the unclamped size.
+    let back = resize_hitbox(
+        pos,
+        corner.size,
+        Some(corner.unsnapped),
+        -growth,
+        false,
+        |_| Vec2::ZERO,
+    );
+    assert_eq!(back.size, size);
+}
+
+/// Times clearing 100 frames with many hitboxes each, which snapshots every frame into the undo
+/// history. Run with `cargo test --release -- --ignored --nocapture clear_all_benchmark`.
+#[test]
+#[ignore]
+fn clear_all_benchmark() {
+    let mut state = EditorState::new(true);
+    state.current_animation.timeline.frames = (0..100).map(|_| frame_with_hitboxes(2000)).collect();
+
+    let started = std::time::Instant::now();
+    state.clear_frames(true);
+    println!("clearing 100 frames took {:?}", started.elapsed());
+}
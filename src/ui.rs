@@ -1,10 +1,24 @@
 use std::{collections::HashMap, str::FromStr, sync::atomic::AtomicBool};
 
-use bevy::{app::AppExit, prelude::*};
+use bevy::{
+    app::AppExit,
+    asset::HandleId,
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
 use bevy_egui::EguiContexts;
 use egui::Context;
+use leafwing_input_manager::{
+    prelude::InputMap,
+    user_input::{InputKind, Modifier, UserInput},
+    Actionlike,
+};
 
-use crate::{Action, EditorState, HitboxPos, InteractionLock, PendingFileDialog, Stages, Tool};
+use crate::{
+    Action, BoxKind, EditorCommand, EditorState, Frame, FrameTag, HitboxIssue, HitboxIssueKind,
+    ImageScale, Input2, MirrorAxis, MotionBake, PendingFileDialog, PendingWrite, Stages, Tool,
+    YAxis, MIN_HITBOX_SIZE,
+};
 
 pub(crate) fn build_ui(commands: &mut Commands) {}
 pub(crate) fn add_systems(app: &mut App) {
@@ -19,8 +33,28 @@ fn ui(
     mut pending_file_dialog: NonSendMut<PendingFileDialog>,
     mut contexts: EguiContexts,
     assets: Res<Assets<Image>>,
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    input_map: Query<&InputMap<Input2>>,
 ) {
     let ctx = contexts.ctx_mut();
+    ui_state.text_field_focused = ctx.memory(|m| m.focus().is_some());
+    if !ui_state.commands_after_commit.is_empty() {
+        // Text fields commit when they lose focus, which they do while being drawn below. The
+        // held back commands run next frame, after that.
+        ctx.memory_mut(|m| {
+            if let Some(id) = m.focus() {
+                m.surrender_focus(id);
+            }
+        });
+        ui_state.text_field_focused = false;
+        let commands = std::mem::take(&mut ui_state.commands_after_commit);
+        editor_state.command_queue.extend(commands);
+    }
+    toasts(ctx, &mut ui_state, &time);
+    if editor_state.show_performance {
+        performance_overlay(ctx, &diagnostics);
+    }
     save_confirmation_window(
         &mut commands,
         ctx,
@@ -29,21 +63,62 @@ fn ui(
         &mut pending_file_dialog,
         &assets,
     );
+    overwrite_confirmation_window(ctx, &mut editor_state, &mut ui_state, &assets);
+    oversized_atlas_window(ctx, &mut editor_state);
+    trim_comparison_window(ctx, &mut editor_state, &mut ui_state, &assets);
+    motion_bake_window(ctx, &mut editor_state, &mut ui_state);
+    hitbox_draft_window(ctx, &mut editor_state);
+    canvas_menu(ctx, &mut editor_state);
+    batch_rename_window(ctx, &mut editor_state, &mut ui_state);
+    hitbox_issues_window(ctx, &mut editor_state, &mut ui_state);
+    missing_images_window(ctx, &mut editor_state, &mut ui_state);
+    command_palette(ctx, &mut editor_state, &mut ui_state, input_map.single());
+    shortcuts_window(ctx, &mut ui_state, input_map.single());
+
+    if editor_state.preview_mode.is_some() {
+        egui::Area::new("preview_controls")
+            .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+            .show(ctx, |ui| {
+                if ui.button("Exit preview").clicked() {
+                    editor_state.toggle_preview_mode();
+                }
+            });
+        return;
+    }
 
     egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
-        ui.set_enabled(editor_state.interaction_lock <= InteractionLock::Playback);
-        toolbar(ui, &mut editor_state);
+        ui.set_enabled(editor_state.interaction_lock.allows_navigation());
+        toolbar(ui, &mut editor_state, &mut ui_state);
     });
 
     egui::TopBottomPanel::bottom("timeline").show(ctx, |ui| {
-        ui.set_enabled(editor_state.interaction_lock <= InteractionLock::Playback);
-        timeline(&mut editor_state, ui);
+        ui.set_enabled(editor_state.interaction_lock.allows_navigation());
+        timeline(&mut editor_state, &mut ui_state, ui);
     });
     egui::SidePanel::right("right_panel").show(ctx, |ui| {
-        ui.set_enabled(editor_state.interaction_lock <= InteractionLock::None);
-        frame_info(&mut editor_state, &mut ui_state, ui);
-        hitbox_info(&mut editor_state, &mut ui_state, ui);
+        ui.set_enabled(editor_state.interaction_lock.allows_canvas());
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            metadata(&mut editor_state, ui);
+            frame_info(&mut editor_state, &mut ui_state, ui);
+            frame_tags(&mut editor_state, ui);
+            hitbox_info(&mut editor_state, &mut ui_state, ui);
+        });
     });
+    ui_state.followed_hitbox = editor_state.currently_selected_box;
+
+    if let Some(pixel) = editor_state.sampled_pixel
+        && !ctx.is_pointer_over_area()
+    {
+        egui::show_tooltip_at_pointer(ctx, egui::Id::new("eyedropper"), |ui| {
+            let [r, g, b, a] = pixel.rgba;
+            ui.horizontal(|ui| {
+                let color = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+                egui::color_picker::show_color(ui, color, egui::vec2(16.0, 16.0));
+                ui.label(format!("{}, {}", pixel.pos.x, pixel.pos.y));
+            });
+            ui.label(format!("R {r}  G {g}  B {b}  A {a}"));
+        });
+    }
 }
 
 fn save_confirmation_window(
@@ -58,14 +133,25 @@ fn save_confirmation_window(
         egui::Window::new("Save?")
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
             .show(ctx, |ui| {
-                ui.label("You have unsaved changes. Do you want to save?");
+                let message = match editor_state.unsaved_edit_count() {
+                    0 => "You have unsaved changes. Do you want to save?".to_string(),
+                    1 => "You have 1 unsaved edit. Do you want to save?".to_string(),
+                    n => format!("You have {n} unsaved edits. Do you want to save?"),
+                };
+                ui.label(message);
                 ui.horizontal(|ui| {
                     if ui.button("Save").clicked() {
-                        editor_state.action_after_save = Some(Box::new(|es| es.exit_now = true));
-                        editor_state.save(pending_file_dialog, assets);
                         ui_state.show_save_menu = false;
-                        if ui_state.save_menu_unlock_on_non_cancel {
-                            editor_state.interaction_lock.release();
+                        match editor_state.save(pending_file_dialog, assets) {
+                            Ok(()) => {
+                                if ui_state.save_menu_unlock_on_non_cancel {
+                                    editor_state.interaction_lock.release();
+                                }
+                            }
+                            Err(err) => {
+                                ui_state.toast(format!("Could not save: {err}"));
+                                editor_state.interaction_lock.release();
+                            }
                         }
                     };
                     if ui.button("Don't save").clicked() {
@@ -78,15 +164,966 @@ fn save_confirmation_window(
                         }
                     };
                     if ui.button("Cancel").clicked() {
-                        ui_state.show_save_menu = false;
-                        editor_state.interaction_lock.release();
+                        ui_state.cancel_save_menu(editor_state);
                     };
                 });
             });
     }
 }
 
-fn toolbar(ui: &mut egui::Ui, editor_state: &mut EditorState) {
+fn toasts(ctx: &mut Context, ui_state: &mut UiState, time: &Time) {
+    ui_state.toasts.retain_mut(|toast| {
+        toast.remaining -= time.delta_seconds();
+        toast.remaining > 0.0
+    });
+
+    if ui_state.toasts.is_empty() {
+        return;
+    }
+
+    egui::Area::new("toasts")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(ctx, |ui| {
+            for toast in &ui_state.toasts {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(&toast.message);
+                });
+            }
+        });
+}
+
+fn oversized_atlas_window(ctx: &mut Context, editor_state: &mut EditorState) {
+    let Some((width, height)) = editor_state.oversized_atlas else {
+        return;
+    };
+
+    egui::Window::new("Spritesheet too large")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "The saved spritesheet is {width}x{height}, which exceeds the max texture size of {}.",
+                editor_state.export_settings.max_texture_size
+            ));
+            ui.label("It may fail to display on some GPUs.");
+            ui.horizontal(|ui| {
+                if !editor_state.export_settings.multi_page_atlas
+                    && ui.button("Enable multi-page packing").clicked()
+                {
+                    editor_state.export_settings.multi_page_atlas = true;
+                    editor_state.oversized_atlas = None;
+                }
+                if ui.button("OK").clicked() {
+                    editor_state.oversized_atlas = None;
+                }
+            });
+        });
+}
+
+/// The current frame as drawn next to how it will be stored after the save-time trim and
+/// padding, so nothing the alpha threshold clips goes unnoticed.
+fn trim_comparison_window(
+    ctx: &mut Context,
+    editor_state: &mut EditorState,
+    ui_state: &mut UiState,
+    assets: &Assets<Image>,
+) {
+    if !editor_state.show_trim_comparison {
+        ui_state.trim_comparison = None;
+        return;
+    }
+    let Some(frame) = editor_state.get_frame(editor_state.current_frame) else {
+        return;
+    };
+    let Some(image) = assets.get(&frame.image) else {
+        return;
+    };
+    // There is no trim preview for a frame whose image can't be converted.
+    let key = editor_state
+        .trim_preview
+        .map(|preview| (frame.image.id(), preview.content, preview.padding));
+    if key
+        != ui_state
+            .trim_comparison
+            .as_ref()
+            .map(|comparison| comparison.key)
+    {
+        ui_state.trim_comparison = None;
+    }
+    if let Some(preview) = editor_state.trim_preview
+        && let Some(key) = key
+        && ui_state.trim_comparison.is_none()
+        && let Ok(drawn) = image.clone().try_into_dynamic()
+    {
+        let drawn = drawn.to_rgba8();
+
+        let [left, top, width, height] = preview.content;
+        let [pad_left, pad_top, pad_right, pad_bottom] = preview.padding;
+        let mut saved =
+            image::RgbaImage::new(pad_left + width + pad_right, pad_top + height + pad_bottom);
+        let content = image::imageops::crop_imm(&drawn, left, top, width, height).to_image();
+        image::imageops::replace(&mut saved, &content, pad_left as i64, pad_top as i64);
+
+        let load = |name: &str, image: &image::RgbaImage| {
+            (image.width() > 0 && image.height() > 0).then(|| {
+                ctx.load_texture(
+                    name,
+                    egui::ColorImage::from_rgba_unmultiplied(
+                        [image.width() as usize, image.height() as usize],
+                        image.as_raw(),
+                    ),
+                    egui::TextureOptions::NEAREST,
+                )
+            })
+        };
+        ui_state.trim_comparison = Some(TrimComparison {
+            key,
+            drawn: load("trim_comparison_drawn", &drawn),
+            saved: load("trim_comparison_saved", &saved),
+        });
+    }
+    let comparison = ui_state.trim_comparison.as_ref();
+
+    let mut open = true;
+    egui::Window::new("Trim comparison")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let Some(comparison) = comparison else {
+                ui.label("Cannot preview this image");
+                return;
+            };
+            // Both sides share one zoom, so the saved cell is shown at its true size
+            // relative to the drawing.
+            let largest = comparison
+                .drawn
+                .iter()
+                .chain(&comparison.saved)
+                .map(|texture| texture.size_vec2().max_elem())
+                .fold(1.0, f32::max);
+            let zoom = 256.0 / largest;
+
+            ui.horizontal_top(|ui| {
+                for (title, texture) in [
+                    ("As drawn", &comparison.drawn),
+                    ("After save", &comparison.saved),
+                ] {
+                    ui.vertical(|ui| {
+                        ui.strong(title);
+                        match texture {
+                            Some(texture) => {
+                                let size = texture.size_vec2();
+                                ui.label(format!("{}x{}", size.x, size.y));
+                                egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                                    ui.image(texture.id(), size * zoom);
+                                });
+                            }
+                            None => {
+                                ui.label("Empty");
+                            }
+                        }
+                    });
+                }
+            });
+            ui.label(format!(
+                "Alpha threshold {}",
+                editor_state.export_settings.alpha_threshold
+            ));
+        });
+    if !open {
+        editor_state.show_trim_comparison = false;
+    }
+}
+
+/// Every bound action grouped by section, read from the input map so it always matches the
+/// actual bindings.
+fn shortcuts_window(ctx: &mut Context, ui_state: &mut UiState, input_map: &InputMap<Input2>) {
+    if !ui_state.show_shortcuts {
+        return;
+    }
+
+    let mut sections: Vec<(&str, Vec<(&str, String)>)> = vec![];
+    for action in Input2::variants() {
+        let Some(bindings) = describe_bindings(input_map, action.clone()) else {
+            continue;
+        };
+        let (section, description) = action.help();
+        match sections.iter_mut().find(|(name, _)| *name == section) {
+            Some((_, entries)) => entries.push((description, bindings)),
+            None => sections.push((section, vec![(description, bindings)])),
+        }
+    }
+
+    egui::Window::new("Keyboard shortcuts")
+        .open(&mut ui_state.show_shortcuts)
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (section, entries) in sections {
+                    ui.strong(section);
+                    egui::Grid::new(section)
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (description, bindings) in entries {
+                                ui.label(description);
+                                ui.monospace(bindings);
+                                ui.end_row();
+                            }
+                        });
+                    ui.add_space(6.0);
+                }
+            });
+        });
+}
+
+/// Every binding of `action`, or `None` if it is unbound.
+fn describe_bindings(input_map: &InputMap<Input2>, action: Input2) -> Option<String> {
+    let bindings = input_map.get(action);
+    (!bindings.is_empty()).then(|| {
+        bindings
+            .iter()
+            .map(describe_input)
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+fn describe_input(input: &UserInput) -> String {
+    match input {
+        UserInput::Single(kind) => describe_input_kind(kind),
+        UserInput::Chord(kinds) => kinds
+            .iter()
+            .map(describe_input_kind)
+            .collect::<Vec<_>>()
+            .join("+"),
+        other => format!("{other:?}"),
+    }
+}
+
+fn describe_input_kind(kind: &InputKind) -> String {
+    match kind {
+        InputKind::Keyboard(KeyCode::BracketLeft) => "[".to_string(),
+        InputKind::Keyboard(KeyCode::BracketRight) => "]".to_string(),
+        InputKind::Keyboard(KeyCode::Slash) => "/".to_string(),
+        InputKind::Keyboard(key) => format!("{key:?}"),
+        InputKind::Modifier(Modifier::Control) => "Ctrl".to_string(),
+        InputKind::Modifier(modifier) => format!("{modifier:?}"),
+        InputKind::Mouse(button) => format!("{button:?} click"),
+        other => format!("{other:?}"),
+    }
+}
+
+/// The editor's own frame rate and frame time, to tell stutter apart from animation timing.
+fn performance_overlay(ctx: &mut Context, diagnostics: &Diagnostics) {
+    let smoothed = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+    };
+    egui::Area::new("performance_overlay")
+        .anchor(egui::Align2::LEFT_TOP, [10.0, 40.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let fps = smoothed(FrameTimeDiagnosticsPlugin::FPS);
+                let frame_time = smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME);
+                match (fps, frame_time) {
+                    (Some(fps), Some(frame_time)) => {
+                        ui.monospace(format!("{fps:5.1} fps  {frame_time:5.2} ms"));
+                    }
+                    _ => {
+                        ui.monospace("Measuring…");
+                    }
+                }
+            });
+        });
+}
+
+fn motion_bake_window(ctx: &mut Context, editor_state: &mut EditorState, ui_state: &mut UiState) {
+    let Some((bake, all_frames)) = ui_state.pending_motion_bake else {
+        return;
+    };
+
+    egui::Window::new("Convert motion?")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .show(ctx, |ui| {
+            let target = if all_frames {
+                "every frame".to_string()
+            } else {
+                format!("frame {}", editor_state.current_frame + 1)
+            };
+            ui.label(format!("Move {} for {target}?", bake.label()));
+            ui.label("This changes where the sprite is drawn when root motion is hidden.");
+            ui.horizontal(|ui| {
+                if ui.button("Convert").clicked() {
+                    let frames = if all_frames {
+                        0..=editor_state
+                            .current_animation
+                            .timeline
+                            .frames
+                            .len()
+                            .saturating_sub(1)
+                    } else {
+                        editor_state.current_frame..=editor_state.current_frame
+                    };
+                    editor_state.bake_motion(bake, frames);
+                    ui_state.pending_motion_bake = None;
+                    editor_state.interaction_lock.release();
+                }
+                if ui.button("Cancel").clicked() {
+                    ui_state.pending_motion_bake = None;
+                    editor_state.interaction_lock.release();
+                }
+            });
+        });
+}
+
+fn overwrite_confirmation_window(
+    ctx: &mut Context,
+    editor_state: &mut EditorState,
+    ui_state: &mut UiState,
+    assets: &Assets<Image>,
+) {
+    let Some(pending) = &ui_state.pending_overwrite else {
+        return;
+    };
+
+    let mut replace = None;
+    egui::Window::new("Replace file?")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "{} already exists. Do you want to replace it?",
+                pending.path.display()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Replace").clicked() {
+                    replace = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    replace = Some(false);
+                }
+            });
+        });
+
+    match replace {
+        Some(true) => {
+            let pending = ui_state.pending_overwrite.take().unwrap();
+            pending.run(editor_state, ui_state, assets);
+        }
+        Some(false) => {
+            // Like cancelling the dialog, this cancels whatever was waiting on the save.
+            ui_state.pending_overwrite = None;
+            editor_state.action_after_save = None;
+            editor_state.interaction_lock.release();
+        }
+        None => {}
+    }
+}
+
+fn hitbox_draft_window(ctx: &mut Context, editor_state: &mut EditorState) {
+    let Some(mut draft) = editor_state.hitbox_draft else {
+        return;
+    };
+    if editor_state.get_frame(draft.frame_index).is_none() {
+        editor_state.hitbox_draft = None;
+        editor_state.interaction_lock.release();
+        return;
+    }
+
+    let mut window = egui::Window::new("New hitbox")
+        .collapsible(false)
+        .resizable(false);
+    window = match draft.screen_pos {
+        Some(pos) => window.fixed_pos([pos.x, pos.y]),
+        None => window.anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0]),
+    };
+
+    let mut create = false;
+    let mut cancel = false;
+    window.show(ctx, |ui| {
+        egui::Grid::new("hitbox_draft").show(ui, |ui| {
+            ui.label("X");
+            ui.add(egui::DragValue::new(&mut draft.pos.x).speed(1.0));
+            ui.label("Y");
+            ui.add(egui::DragValue::new(&mut draft.pos.y).speed(1.0));
+            ui.end_row();
+            ui.label("Width");
+            ui.add(
+                egui::DragValue::new(&mut draft.size.x)
+                    .speed(1.0)
+                    .clamp_range(MIN_HITBOX_SIZE..=f32::MAX),
+            );
+            ui.label("Height");
+            ui.add(
+                egui::DragValue::new(&mut draft.size.y)
+                    .speed(1.0)
+                    .clamp_range(MIN_HITBOX_SIZE..=f32::MAX),
+            );
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            create = ui.button("Create").clicked();
+            cancel = ui.button("Cancel").clicked();
+        });
+    });
+    create |= ctx.input(|i| i.key_pressed(egui::Key::Enter));
+    cancel |= ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+    if create {
+        editor_state.hitbox_draft = None;
+        editor_state.interaction_lock.release();
+        editor_state.create_hitbox_with_pos(
+            draft.frame_index,
+            draft.pos.round(),
+            draft.size.round(),
+            BoxKind::Hit,
+        );
+    } else if cancel {
+        editor_state.hitbox_draft = None;
+        editor_state.interaction_lock.release();
+    } else {
+        editor_state.hitbox_draft = Some(draft);
+    }
+}
+
+fn canvas_menu(ctx: &mut Context, editor_state: &mut EditorState) {
+    let Some(menu) = editor_state.canvas_menu else {
+        return;
+    };
+    let hitbox = menu
+        .hitbox
+        .and_then(|id| editor_state.current_animation.hitboxes.get(&id).cloned());
+    if !editor_state.interaction_lock.allows_canvas()
+        || editor_state.get_frame(menu.frame_index).is_none()
+        || (menu.hitbox.is_some() && hitbox.is_none())
+    {
+        editor_state.canvas_menu = None;
+        return;
+    }
+
+    let mut close = false;
+    let response = egui::Area::new("canvas_menu")
+        .order(egui::Order::Foreground)
+        .fixed_pos([menu.screen_pos.x, menu.screen_pos.y])
+        .show(ctx, |ui| {
+            egui::Frame::menu(ui.style()).show(ui, |ui| {
+                let Some(hitbox) = hitbox else {
+                    if ui.button("Create hitbox here").clicked() {
+                        let size = editor_state.last_hitbox_size;
+                        let pos = (menu.pos + size * Vec2::new(-0.5, 0.5)).round();
+                        close = true;
+                        editor_state.canvas_menu = None;
+                        editor_state.begin_hitbox_creation(
+                            menu.frame_index,
+                            pos,
+                            size,
+                            Some(menu.screen_pos),
+                        );
+                    }
+                    return;
+                };
+
+                ui.label(egui::RichText::new(&hitbox.desc).weak());
+                if ui.button("Duplicate").clicked() {
+                    editor_state.duplicate_hitbox(hitbox.id);
+                    close = true;
+                }
+                for kind in BoxKind::ALL {
+                    if kind != hitbox.kind
+                        && ui
+                            .button(format!("Make {}", kind.label().to_lowercase()))
+                            .clicked()
+                    {
+                        editor_state.do_action(Action::SetHitboxKind {
+                            id: hitbox.id,
+                            from: hitbox.kind,
+                            to: kind,
+                        });
+                        close = true;
+                    }
+                }
+                if ui.button("Disable on this frame").clicked() {
+                    editor_state.do_action(Action::ToggleHitboxEnabled {
+                        frame_index: menu.frame_index,
+                        id: hitbox.id,
+                    });
+                    close = true;
+                }
+                ui.separator();
+                if ui.button("Delete").clicked() {
+                    editor_state.delete_hitbox(hitbox.id);
+                    close = true;
+                }
+            });
+        })
+        .response;
+
+    // Right-clicking elsewhere moves the menu instead, from the canvas.
+    let pressed_elsewhere = ctx.input(|i| {
+        i.pointer.primary_pressed()
+            && i.pointer
+                .interact_pos()
+                .is_some_and(|pos| !response.rect.contains(pos))
+    });
+    if close || pressed_elsewhere || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        editor_state.canvas_menu = None;
+    }
+}
+
+fn batch_rename_window(ctx: &mut Context, editor_state: &mut EditorState, ui_state: &mut UiState) {
+    let Some(template) = &mut ui_state.batch_rename else {
+        return;
+    };
+
+    let mut close = false;
+    egui::Window::new("Rename hitboxes")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(template)
+                    .on_hover_text("{i} is replaced by the box's number, counting from 0");
+            });
+            let preview = editor_state.batch_rename_preview(template);
+            for (id, to) in &preview {
+                ui.label(format!(
+                    "{} → {to}",
+                    editor_state.current_animation.hitboxes[id].desc
+                ));
+            }
+            if preview.len() > 1 && !template.contains("{i}") {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    "Without {i} every box gets the same name",
+                );
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!preview.is_empty(), egui::Button::new("Rename"))
+                    .clicked()
+                {
+                    editor_state.batch_rename_hitboxes(template.trim());
+                    close = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+    if close {
+        ui_state.batch_rename = None;
+    }
+}
+
+fn missing_images_window(
+    ctx: &mut Context,
+    editor_state: &mut EditorState,
+    ui_state: &mut UiState,
+) {
+    let Some(missing) = &editor_state.missing_images else {
+        return;
+    };
+    if !ui_state.show_missing_images {
+        return;
+    }
+
+    let mut open = true;
+    let mut locate = None;
+    egui::Window::new("Missing images")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("These frames show placeholders. Locate their images to use them; the new locations are recorded in the sequence when you next save.");
+            egui::Grid::new("missing_images").num_columns(3).show(ui, |ui| {
+                for image in &missing.images {
+                    ui.label(format!("Frame {}", image.index + 1));
+                    match &image.located {
+                        Some(path) => ui.label(path.display().to_string()),
+                        None => ui.weak("Missing"),
+                    };
+                    if ui
+                        .add_enabled(
+                            editor_state.interaction_lock.allows_file_ops(),
+                            egui::Button::new("Locate…"),
+                        )
+                        .clicked()
+                    {
+                        locate = Some(image.index);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    if let Some(index) = locate {
+        editor_state
+            .command_queue
+            .push(EditorCommand::LocateImage(index));
+    }
+    ui_state.show_missing_images = open;
+}
+
+fn hitbox_issues_window(ctx: &mut Context, editor_state: &mut EditorState, ui_state: &mut UiState) {
+    let Some(issues) = &ui_state.hitbox_issues else {
+        return;
+    };
+
+    let mut open = true;
+    let mut recheck = false;
+    egui::Window::new("Hitbox check")
+        .open(&mut open)
+        .default_height(300.0)
+        .show(ctx, |ui| {
+            if issues.is_empty() {
+                ui.label("No problems found.");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for issue in issues {
+                    let [a, b] = issue.ids.map(|id| {
+                        editor_state
+                            .current_animation
+                            .hitboxes
+                            .get(&id)
+                            .map_or_else(|| format!("#{id}"), |hitbox| hitbox.desc.clone())
+                    });
+                    let text = match issue.kind {
+                        HitboxIssueKind::Duplicate => format!("{a} and {b} are identical"),
+                        HitboxIssueKind::HurtboxInsideCollisionBox => {
+                            format!("{a} is inside collision box {b}")
+                        }
+                    };
+                    let frame = issue.frame_index;
+                    if ui
+                        .link(format!("Frame {}: {text}", frame + 1))
+                        .on_hover_text("Go to the frame and select both boxes")
+                        .clicked()
+                        && editor_state.get_frame(frame).is_some()
+                    {
+                        editor_state.current_frame = frame;
+                        editor_state.currently_selected_box = Some(issue.ids[0]);
+                        editor_state.selected_boxes = issue.ids.into_iter().collect();
+                    }
+                }
+            });
+            ui.separator();
+            recheck = ui.button("Check again").clicked();
+        });
+
+    if recheck {
+        ui_state.hitbox_issues = Some(editor_state.check_hitboxes());
+    } else if !open {
+        ui_state.hitbox_issues = None;
+    }
+}
+
+#[derive(Default)]
+struct CommandPalette {
+    query: String,
+    /// Index into the currently matching entries.
+    selected: usize,
+}
+
+/// Everything the command palette offers, with the action of its shortcut if there is one.
+fn palette_entries() -> Vec<(&'static str, Option<Input2>, EditorCommand)> {
+    vec![
+        ("File: New", Some(Input2::New), EditorCommand::New),
+        ("File: Open…", Some(Input2::Open), EditorCommand::Open),
+        ("File: Save", Some(Input2::Save), EditorCommand::Save),
+        (
+            "File: Save as…",
+            Some(Input2::SaveAs),
+            EditorCommand::SaveAs,
+        ),
+        (
+            "Import: PNG sequence folder…",
+            None,
+            EditorCommand::ImportSequence,
+        ),
+        (
+            "Import: Set anchors from pivot markers",
+            None,
+            EditorCommand::ApplyPivotMarkers,
+        ),
+        (
+            "Export: Selected frames as new animation…",
+            None,
+            EditorCommand::ExportSelection,
+        ),
+        (
+            "Export: Current frame as PNG…",
+            None,
+            EditorCommand::ExportFramePng { trimmed: false },
+        ),
+        (
+            "Export: Current frame as trimmed PNG…",
+            None,
+            EditorCommand::ExportFramePng { trimmed: true },
+        ),
+        ("Export: PNG sequence…", None, EditorCommand::ExportSequence),
+        (
+            "Export: Hitbox data by kind…",
+            None,
+            EditorCommand::ExportBoxesByKind,
+        ),
+        (
+            "Export: Capture canvas as PNG…",
+            None,
+            EditorCommand::CaptureCanvas,
+        ),
+        ("Edit: Undo", Some(Input2::Undo), EditorCommand::Undo),
+        ("Edit: Redo", Some(Input2::Redo), EditorCommand::Redo),
+        (
+            "Edit: Select all hitboxes",
+            Some(Input2::SelectAllHitboxes),
+            EditorCommand::SelectAllHitboxes,
+        ),
+        (
+            "Edit: Deselect all hitboxes",
+            Some(Input2::DeselectAllHitboxes),
+            EditorCommand::DeselectAllHitboxes,
+        ),
+        (
+            "Edit: Invert hitbox selection",
+            None,
+            EditorCommand::InvertHitboxSelection,
+        ),
+        (
+            "Edit: Select by kind: hitboxes",
+            None,
+            EditorCommand::SelectHitboxesByKind(BoxKind::Hit),
+        ),
+        (
+            "Edit: Select by kind: hurtboxes",
+            None,
+            EditorCommand::SelectHitboxesByKind(BoxKind::Hurt),
+        ),
+        (
+            "Edit: Select by kind: collision boxes",
+            None,
+            EditorCommand::SelectHitboxesByKind(BoxKind::Collision),
+        ),
+        (
+            "View: Keyboard shortcuts",
+            Some(Input2::ShowShortcuts),
+            EditorCommand::ToggleShortcuts,
+        ),
+        (
+            "Edit: Mirror hitboxes left to right",
+            None,
+            EditorCommand::MirrorHitboxes(MirrorAxis::Vertical),
+        ),
+        (
+            "Edit: Mirror hitboxes top to bottom",
+            None,
+            EditorCommand::MirrorHitboxes(MirrorAxis::Horizontal),
+        ),
+        (
+            "Edit: Center selected hitbox on anchor",
+            None,
+            EditorCommand::CenterHitboxOnAnchor,
+        ),
+        (
+            "Edit: Move anchor to selected hitbox",
+            None,
+            EditorCommand::SnapAnchorToHitbox,
+        ),
+        (
+            "Edit: Check hitboxes for conflicts",
+            None,
+            EditorCommand::CheckHitboxes,
+        ),
+        (
+            "Frame: Add from files…",
+            Some(Input2::AddFrame),
+            EditorCommand::AddFrame,
+        ),
+        (
+            "Frame: Paste from clipboard",
+            Some(Input2::PasteFrame),
+            EditorCommand::PasteFrame,
+        ),
+        (
+            "Frame: Duplicate current",
+            Some(Input2::DuplicateFrame),
+            EditorCommand::DuplicateFrame,
+        ),
+        (
+            "Frame: Delete current",
+            Some(Input2::DeleteFrame),
+            EditorCommand::DeleteFrame,
+        ),
+        (
+            "Frame: Previous",
+            Some(Input2::PrevFrame),
+            EditorCommand::PrevFrame,
+        ),
+        (
+            "Frame: Next",
+            Some(Input2::NextFrame),
+            EditorCommand::NextFrame,
+        ),
+        (
+            "Tool: Select",
+            Some(Input2::ToolSelect),
+            EditorCommand::SelectTool(Tool::Select),
+        ),
+        (
+            "Tool: Move anchor",
+            Some(Input2::ToolMoveAnchor),
+            EditorCommand::SelectTool(Tool::MoveAnchor),
+        ),
+        (
+            "Tool: Move root motion",
+            None,
+            EditorCommand::SelectTool(Tool::MoveRootMotion),
+        ),
+        (
+            "Tool: Eyedropper",
+            None,
+            EditorCommand::SelectTool(Tool::Eyedropper),
+        ),
+        (
+            "View: Toggle playback",
+            Some(Input2::TogglePlayback),
+            EditorCommand::TogglePlayback,
+        ),
+        (
+            "View: Toggle preview mode",
+            Some(Input2::TogglePreview),
+            EditorCommand::TogglePreview,
+        ),
+        (
+            "View: Fit to current frame",
+            Some(Input2::FitView),
+            EditorCommand::FitView,
+        ),
+        ("View: Toggle hitboxes", None, EditorCommand::ToggleHitboxes),
+        ("View: Toggle grid", None, EditorCommand::ToggleGrid),
+        (
+            "View: Toggle editor FPS overlay",
+            None,
+            EditorCommand::TogglePerformanceOverlay,
+        ),
+    ]
+}
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence, favouring runs of
+/// consecutive characters and matches at word starts. `None` if it doesn't match.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + candidate[next..].iter().position(|&c| c == q)?;
+        score += 1;
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 2;
+        }
+        last_match = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+fn command_palette(
+    ctx: &mut Context,
+    editor_state: &mut EditorState,
+    ui_state: &mut UiState,
+    input_map: &InputMap<Input2>,
+) {
+    let Some(palette) = &mut ui_state.command_palette else {
+        return;
+    };
+
+    let mut matches = palette_entries()
+        .into_iter()
+        .filter_map(|(name, shortcut, command)| {
+            let shortcut = shortcut.and_then(|action| describe_bindings(input_map, action));
+            Some((fuzzy_score(&palette.query, name)?, name, shortcut, command))
+        })
+        .collect::<Vec<_>>();
+    // Stable, so equally good matches keep their listed order.
+    matches.sort_by_key(|(score, ..)| std::cmp::Reverse(*score));
+
+    let (up, down, enter, escape) = ctx.input(|i| {
+        (
+            i.key_pressed(egui::Key::ArrowUp),
+            i.key_pressed(egui::Key::ArrowDown),
+            i.key_pressed(egui::Key::Enter),
+            i.key_pressed(egui::Key::Escape),
+        )
+    });
+    if up {
+        palette.selected = palette.selected.saturating_sub(1);
+    }
+    if down {
+        palette.selected += 1;
+    }
+    palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+    let mut chosen = enter.then_some(palette.selected);
+    egui::Window::new("Command palette")
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text("Type a command…")
+                    .desired_width(320.0),
+            );
+            response.request_focus();
+            if response.changed() {
+                palette.selected = 0;
+            }
+
+            if matches.is_empty() {
+                ui.weak("No matching commands");
+            }
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for (i, (_, name, shortcut, _)) in matches.iter().enumerate() {
+                        let selected = i == palette.selected;
+                        let response = ui
+                            .horizontal(|ui| {
+                                let label = ui.selectable_label(selected, *name);
+                                if let Some(shortcut) = shortcut {
+                                    ui.weak(shortcut.as_str());
+                                }
+                                label
+                            })
+                            .inner;
+                        if selected && (up || down) {
+                            response.scroll_to_me(None);
+                        }
+                        if response.clicked() {
+                            chosen = Some(i);
+                        }
+                    }
+                });
+        });
+
+    let chosen = chosen.and_then(|i| matches.into_iter().nth(i));
+    if chosen.is_some() || escape {
+        ui_state.command_palette = None;
+        editor_state.interaction_lock.release();
+    }
+    if let Some((_, _, _, command)) = chosen {
+        editor_state.command_queue.push(command);
+    }
+}
+
+fn toolbar(ui: &mut egui::Ui, editor_state: &mut EditorState, ui_state: &mut UiState) {
     ui.horizontal_centered(|ui| {
         let mut button = |tool: Tool, msg: &str| {
             if ui
@@ -100,22 +1137,573 @@ fn toolbar(ui: &mut egui::Ui, editor_state: &mut EditorState) {
         button(Tool::Select, "Select");
         button(Tool::MoveAnchor, "Move Anchor");
         button(Tool::MoveRootMotion, "Move Root Motion");
-        // button(Tool::CreateHitbox, "Create Hitbox");
-        // button(Tool::CreateHurtbox, "Create Hurtbox");
+        button(Tool::CreateHitbox, "Create Hitbox");
+        button(Tool::CreateHurtbox, "Create Hurtbox");
+        button(Tool::CreateCollisionbox, "Create Collision Box");
+        button(Tool::Eyedropper, "Eyedropper");
 
         ui.separator();
 
-        let checked = &mut editor_state.always_show_root_motion;
-        ui.checkbox(checked, "Always show root motion");
+        let checked = &mut editor_state.always_show_root_motion;
+        ui.checkbox(checked, "Always show root motion");
+
+        let checked = &mut editor_state.hitboxes_follow_root_motion;
+        ui.checkbox(checked, "Hitboxes follow root motion")
+            .on_hover_text("When off, hitboxes stay at their local positions while root motion moves the sprite");
+
+        ui.separator();
+
+        let checked = &mut editor_state.interpolate_playback;
+        ui.checkbox(checked, "Tween playback");
+
+        let checked = &mut editor_state.show_performance;
+        ui.checkbox(checked, "Show editor FPS")
+            .on_hover_text("Show the editor's own frame rate and frame time");
+
+        let fps = &mut editor_state.current_animation.fps;
+        if ui
+            .add(
+                egui::DragValue::new(fps)
+                    .clamp_range(1..=240)
+                    .suffix(" fps"),
+            )
+            .on_hover_text("Tick rate for playback and millisecond conversions, saved with the animation")
+            .changed()
+        {
+            editor_state.edit_outside_history();
+        }
+
+        ui.separator();
+
+        let animation = &editor_state.current_animation;
+        let frame_count = animation.timeline.frames.len();
+        let total_ticks = animation.total_ticks();
+        ui.label(format!("{frame_count} frames"))
+            .on_hover_text(format!(
+                "{total_ticks} ticks, {:.0} ms",
+                animation.ticks_to_millis(total_ticks)
+            ));
+        ui_state.placeholder_count = ui_state.placeholder_count.max(1);
+        ui.add(
+            egui::DragValue::new(&mut ui_state.placeholder_count)
+                .clamp_range(1..=100)
+                .prefix("+"),
+        );
+        if ui
+            .button("Add placeholders")
+            .on_hover_text("Append checkerboard frames to block out timing")
+            .clicked()
+        {
+            editor_state
+                .command_queue
+                .push(EditorCommand::AddPlaceholderFrames(ui_state.placeholder_count));
+        }
+
+        ui.separator();
+
+        let checked = &mut editor_state.show_hitboxes;
+        ui.checkbox(checked, "Show hitboxes");
+
+        let checked = &mut editor_state.resize_from_center;
+        ui.checkbox(checked, "Resize from center")
+            .on_hover_text("Shift-drag resizes hitboxes around their center");
+
+        let checked = &mut editor_state.snap_hitboxes;
+        ui.checkbox(checked, "Snap hitboxes")
+            .on_hover_text("Snap dragged hitbox edges to the edges of the other hitboxes");
+
+        ui.separator();
+
+        let checked = &mut editor_state.show_grid;
+        ui.checkbox(checked, "Show grid");
+
+        ui.label("Marker size");
+        ui.add(egui::DragValue::new(&mut editor_state.marker_size).clamp_range(1.0..=100.0));
+
+        let checked = &mut editor_state.grid_at_anchor;
+        ui.checkbox(checked, "Grid at anchor");
+
+        ui.separator();
+
+        let checked = &mut editor_state.show_trim_preview;
+        ui.checkbox(checked, "Preview save trim");
+
+        let checked = &mut editor_state.show_saved_cell;
+        ui.checkbox(checked, "Show saved cell");
+
+        let checked = &mut editor_state.show_trim_comparison;
+        ui.checkbox(checked, "Compare before/after trim");
+
+        ui.separator();
+
+        ui.label("Sprite tint");
+        ui.color_edit_button_rgba_unmultiplied(&mut editor_state.sprite_tint);
+        if ui
+            .add_enabled(
+                editor_state.sprite_tint != [1.0; 4],
+                egui::Button::new("Reset"),
+            )
+            .clicked()
+        {
+            editor_state.sprite_tint = [1.0; 4];
+        }
+
+        ui.separator();
+
+        #[cfg(feature = "live-link")]
+        {
+            ui.checkbox(&mut editor_state.live_link_enabled, "Live link")
+                .on_hover_text(format!(
+                    "Stream gameplay data as JSON to clients on localhost:{}",
+                    crate::live_link::LIVE_LINK_PORT
+                ));
+
+            ui.separator();
+        }
+
+        if ui
+            .button("Preview")
+            .on_hover_text("Hide the editing UI and play the animation (P)")
+            .clicked()
+        {
+            editor_state.toggle_preview_mode();
+        }
+
+        ui.separator();
+
+        ui.add_enabled_ui(editor_state.interaction_lock.allows_canvas(), |ui| {
+            ui.menu_button("Edit", |ui| {
+                if ui.button("Paste frame from clipboard").clicked() {
+                    editor_state.command_queue.push(EditorCommand::PasteFrame);
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        editor_state.get_frame(editor_state.current_frame).is_some(),
+                        egui::Button::new("Duplicate current frame (Ctrl+D)"),
+                    )
+                    .clicked()
+                {
+                    editor_state.command_queue.push(EditorCommand::DuplicateFrame);
+                    ui.close_menu();
+                }
+                if ui.button("Clear all frames").clicked() {
+                    editor_state.clear_frames(true);
+                    ui.close_menu();
+                }
+                if ui.button("Clear all frames and hitboxes").clicked() {
+                    editor_state.clear_frames(false);
+                    ui.close_menu();
+                }
+
+                ui.separator();
+
+                if ui
+                    .add_enabled(
+                        editor_state.get_frame(editor_state.current_frame).is_some(),
+                        egui::Button::new("Select all hitboxes (Ctrl+A)"),
+                    )
+                    .clicked()
+                {
+                    editor_state.select_all_hitboxes();
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        !editor_state.selected_boxes.is_empty(),
+                        egui::Button::new("Deselect all hitboxes (Ctrl+Shift+A)"),
+                    )
+                    .clicked()
+                {
+                    editor_state.deselect_all_hitboxes();
+                    ui.close_menu();
+                }
+                ui.add_enabled_ui(
+                    editor_state.get_frame(editor_state.current_frame).is_some(),
+                    |ui| {
+                        if ui.button("Invert hitbox selection").clicked() {
+                            editor_state.invert_hitbox_selection();
+                            ui.close_menu();
+                        }
+                        ui.menu_button("Select by kind", |ui| {
+                            for kind in BoxKind::ALL {
+                                if ui.button(format!("{}es", kind.label())).clicked() {
+                                    editor_state.select_hitboxes_by_kind(kind);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    },
+                );
+                if ui
+                    .add_enabled(
+                        !editor_state.selected_boxes.is_empty(),
+                        egui::Button::new("Rename selected hitboxes…"),
+                    )
+                    .clicked()
+                {
+                    ui_state.batch_rename = Some("Hitbox {i}".to_string());
+                    ui.close_menu();
+                }
+                ui.menu_button("Mirror hitboxes", |ui| {
+                    for axis in [MirrorAxis::Vertical, MirrorAxis::Horizontal] {
+                        if ui
+                            .add_enabled(
+                                editor_state.get_frame(editor_state.current_frame).is_some(),
+                                egui::Button::new(format!("Mirror {}", axis.label())),
+                            )
+                            .on_hover_text(
+                                "Across the anchor; only the selected hitboxes if any are selected",
+                            )
+                            .clicked()
+                        {
+                            editor_state.mirror_hitboxes(axis);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                if ui
+                    .button("Check hitboxes…")
+                    .on_hover_text("List overlapping boxes that look like mistakes, on every frame")
+                    .clicked()
+                {
+                    editor_state
+                        .command_queue
+                        .push(EditorCommand::CheckHitboxes);
+                    ui.close_menu();
+                }
+
+                ui.separator();
+
+                if let Some(reference) = editor_state.reference_frame {
+                    if ui
+                        .button(format!("Unpin reference frame {}", reference + 1))
+                        .clicked()
+                    {
+                        editor_state.reference_frame = None;
+                        ui.close_menu();
+                    }
+                } else if ui
+                    .add_enabled(
+                        editor_state.get_frame(editor_state.current_frame).is_some(),
+                        egui::Button::new("Pin current frame as reference"),
+                    )
+                    .on_hover_text("Overlay this frame on the others to compare them")
+                    .clicked()
+                {
+                    editor_state.reference_frame = Some(editor_state.current_frame);
+                    ui.close_menu();
+                }
+
+                ui.separator();
+
+                if ui.button("Round hitboxes to whole pixels").clicked() {
+                    editor_state.round_hitboxes();
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Round offsets and root motion to whole pixels")
+                    .clicked()
+                {
+                    editor_state.round_offsets();
+                    ui.close_menu();
+                }
+
+                ui.menu_button("Convert motion", |ui| {
+                    for bake in [MotionBake::RootMotionToOffset, MotionBake::OffsetToRootMotion] {
+                        for all_frames in [false, true] {
+                            let target = if all_frames { "all frames" } else { "current frame" };
+                            if ui
+                                .button(format!("Bake {} ({target})", bake.label()))
+                                .clicked()
+                            {
+                                ui_state.pending_motion_bake = Some((bake, all_frames));
+                                editor_state.interaction_lock.lock_modal();
+                                ui.close_menu();
+                            }
+                        }
+                    }
+                });
+
+                ui.separator();
+
+                ui.menu_button("Scale animation", |ui| {
+                    for scale in ImageScale::ALL {
+                        if scale != ImageScale::One && ui.button(scale.label()).clicked() {
+                            editor_state
+                                .command_queue
+                                .push(EditorCommand::ScaleAnimation(scale));
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("Scale imported frames", |ui| {
+                    for scale in ImageScale::ALL {
+                        ui.radio_value(&mut editor_state.import_scale, scale, scale.label());
+                    }
+                });
+            });
+        });
+
+        ui.menu_button("Import", |ui| {
+            if ui
+                .add_enabled(
+                    editor_state.interaction_lock.allows_file_ops(),
+                    egui::Button::new("PNG sequence folder…"),
+                )
+                .on_hover_text("Open a folder written by \"Export as PNG sequence\"")
+                .clicked()
+            {
+                editor_state
+                    .command_queue
+                    .push(EditorCommand::ImportSequence);
+                ui.close_menu();
+            }
+            if editor_state.missing_images.is_some() && ui.button("Missing images…").clicked() {
+                ui_state.show_missing_images();
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Pivot marker color");
+                ui.color_edit_button_srgb(&mut editor_state.pivot_marker_color);
+            });
+            let has_frames = !editor_state.current_animation.timeline.frames.is_empty();
+            if ui
+                .add_enabled(
+                    has_frames && editor_state.interaction_lock.allows_canvas(),
+                    egui::Button::new("Set anchors from pivot markers"),
+                )
+                .on_hover_text(
+                    "Move each frame's anchor to the pixel of the marker color and erase that pixel",
+                )
+                .clicked()
+            {
+                editor_state
+                    .command_queue
+                    .push(EditorCommand::ApplyPivotMarkers);
+                ui.close_menu();
+            }
+        });
+
+        ui.menu_button("Export", |ui| {
+            let label = match editor_state.selected_frames {
+                Some((start, end)) => {
+                    format!("Export frames {}-{} as new animation…", start + 1, end + 1)
+                }
+                None => "Export selected frames as new animation…".to_owned(),
+            };
+            if ui
+                .add_enabled(
+                    editor_state.selected_frames.is_some()
+                        && editor_state.interaction_lock.allows_file_ops(),
+                    egui::Button::new(label),
+                )
+                .on_disabled_hover_text("Shift-click a frame in the timeline to select a range")
+                .clicked()
+            {
+                editor_state
+                    .command_queue
+                    .push(EditorCommand::ExportSelection);
+                ui.close_menu();
+            }
+
+            let has_frame = editor_state.get_frame(editor_state.current_frame).is_some();
+            ui.add_enabled_ui(has_frame, |ui| {
+                if ui.button("Export current frame as PNG…").clicked() {
+                    editor_state
+                        .command_queue
+                        .push(EditorCommand::ExportFramePng { trimmed: false });
+                    ui.close_menu();
+                }
+                if ui.button("Export current frame as trimmed PNG…").clicked() {
+                    editor_state
+                        .command_queue
+                        .push(EditorCommand::ExportFramePng { trimmed: true });
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Export as PNG sequence…")
+                    .on_hover_text(format!(
+                        "Write every frame as a PNG into a folder, with the rest of the animation in {}",
+                        crate::SEQUENCE_INFO_FILE
+                    ))
+                    .clicked()
+                {
+                    editor_state
+                        .command_queue
+                        .push(EditorCommand::ExportSequence);
+                    ui.close_menu();
+                }
+            });
+
+            if ui
+                .add_enabled(
+                    editor_state.interaction_lock.allows_file_ops(),
+                    egui::Button::new("Export hitbox data by kind…"),
+                )
+                .on_hover_text(
+                    "Write hitboxes, hurtboxes and collision boxes into separate JSON files in a folder",
+                )
+                .clicked()
+            {
+                editor_state
+                    .command_queue
+                    .push(EditorCommand::ExportBoxesByKind);
+                ui.close_menu();
+            }
+
+            if ui
+                .add_enabled(
+                    editor_state.interaction_lock.allows_file_ops(),
+                    egui::Button::new("Capture canvas as PNG…"),
+                )
+                .on_hover_text("Save the canvas as shown, with hitboxes and background")
+                .clicked()
+            {
+                editor_state
+                    .command_queue
+                    .push(EditorCommand::CaptureCanvas);
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Max texture size");
+                ui.add(
+                    egui::DragValue::new(&mut editor_state.export_settings.max_texture_size)
+                        .clamp_range(1..=u16::MAX as u32),
+                );
+            });
+            ui.checkbox(
+                &mut editor_state.export_settings.multi_page_atlas,
+                "Split oversized spritesheets into pages",
+            );
+            ui.checkbox(
+                &mut editor_state.export_settings.tight_packing,
+                "Tight packing",
+            )
+            .on_hover_text(
+                "Trim each frame to its content and store its own rect, instead of padding all frames to a common grid cell",
+            );
+            ui.checkbox(
+                &mut editor_state.export_settings.collapse_held_frames,
+                "Collapse held frames",
+            )
+            .on_hover_text(
+                "Save runs of identical consecutive frames as one longer frame. The frames stay separate in the editor until the file is reopened",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Trim alpha threshold");
+                ui.add(egui::DragValue::new(
+                    &mut editor_state.export_settings.alpha_threshold,
+                ))
+                .on_hover_text("Pixels with this alpha or less are trimmed away as empty");
+            });
+        });
+
+        ui.menu_button("Lightbox", |ui| {
+            if ui
+                .add_enabled(
+                    editor_state.interaction_lock.allows_file_ops(),
+                    egui::Button::new("Load guide image…"),
+                )
+                .on_hover_text("Show an image behind the canvas to trace or match; it is never saved or exported")
+                .clicked()
+            {
+                editor_state
+                    .command_queue
+                    .push(EditorCommand::LoadLightbox);
+                ui.close_menu();
+            }
+            let lightbox = &mut editor_state.lightbox;
+            ui.add_enabled_ui(lightbox.image.is_some(), |ui| {
+                egui::Grid::new("lightbox_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("X");
+                        ui.add(egui::DragValue::new(&mut lightbox.position.x));
+                        ui.end_row();
+                        ui.label("Y");
+                        ui.add(egui::DragValue::new(&mut lightbox.position.y));
+                        ui.end_row();
+                        ui.label("Scale");
+                        ui.add(
+                            egui::DragValue::new(&mut lightbox.scale)
+                                .speed(0.01)
+                                .clamp_range(0.01..=100.0),
+                        );
+                        ui.end_row();
+                        ui.label("Opacity");
+                        ui.add(egui::Slider::new(&mut lightbox.opacity, 0.0..=1.0));
+                        ui.end_row();
+                    });
+                if ui.button("Remove guide image").clicked() {
+                    lightbox.image = None;
+                    ui.close_menu();
+                }
+            });
+        });
+
+        ui.menu_button("Workspace", |ui| {
+            let mut apply = None;
+            let mut delete = None;
+            for (index, preset) in editor_state.workspace_presets.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button(&preset.name).clicked() {
+                        apply = Some(index);
+                    }
+                    if ui.small_button("🗑").on_hover_text("Delete preset").clicked() {
+                        delete = Some(index);
+                    }
+                });
+            }
+            if editor_state.workspace_presets.is_empty() {
+                ui.weak("No presets yet");
+            }
+            if let Some(index) = apply {
+                editor_state.apply_workspace_preset(index);
+                ui.close_menu();
+            }
+            if let Some(index) = delete
+                && let Err(err) = editor_state.delete_workspace_preset(index)
+            {
+                ui_state.toast(format!("Could not save workspace presets: {err}"));
+            }
+
+            ui.separator();
 
-        ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut ui_state.preset_name);
+                let name = ui_state.preset_name.trim().to_string();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("Save current view"))
+                    .on_hover_text("Toggles, tool, zoom, camera position and sprite tint; a preset with the same name is replaced")
+                    .clicked()
+                {
+                    if let Err(err) = editor_state.save_workspace_preset(name) {
+                        ui_state.toast(format!("Could not save workspace presets: {err}"));
+                    }
+                    ui_state.preset_name.clear();
+                }
+            });
+        });
 
-        let checked = &mut editor_state.show_hitboxes;
-        ui.checkbox(checked, "Show hitboxes");
+        ui.menu_button("Help", |ui| {
+            if ui.button("Keyboard shortcuts (?)").clicked() {
+                ui_state.toggle_shortcuts();
+                ui.close_menu();
+            }
+        });
     });
 }
 
-fn timeline(editor_state: &mut EditorState, ui: &mut egui::Ui) {
+fn timeline(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut egui::Ui) {
+    // Applied after the loop, since it changes the number of frames.
+    let mut frame_action = None;
     ui.group(|ui| {
         egui::ScrollArea::horizontal().show(ui, |ui| {
             ui.horizontal(|ui| {
@@ -124,43 +1712,352 @@ fn timeline(editor_state: &mut EditorState, ui: &mut egui::Ui) {
                         ui.separator();
                     }
 
-                    ui.allocate_ui_with_layout(
+                    let response = ui.allocate_ui_with_layout(
                         egui::Vec2::new(30.0, 100.0),
                         egui::Layout::top_down(egui::Align::Center),
                         |ui| {
-                            if ui
-                                .add_enabled(
-                                    i != editor_state.current_frame,
-                                    egui::Button::new(format!("{}", i + 1))
-                                        .min_size(egui::Vec2::new(30.0, 0.0)),
-                                )
-                                .clicked()
+                            let frame = &editor_state.current_animation.timeline.frames[i];
+                            let mut button = egui::Button::new(format!("{}", i + 1))
+                                .min_size(egui::Vec2::new(30.0, 0.0));
+                            if editor_state
+                                .selected_frames
+                                .is_some_and(|(start, end)| (start..=end).contains(&i))
+                            {
+                                button = button.fill(ui.visuals().selection.bg_fill);
+                            }
+                            let response = ui
+                                .add_enabled(i != editor_state.current_frame, button)
+                                .on_hover_ui(|ui| frame_tooltip(ui, frame))
+                                .on_disabled_hover_ui(|ui| frame_tooltip(ui, frame));
+                            if i == editor_state.current_frame
+                                && ui_state.timeline_followed_frame != Some(i)
                             {
-                                editor_state.current_frame = i;
+                                response.scroll_to_me(None);
+                                ui_state.timeline_followed_frame = Some(i);
+                            }
+                            if response.clicked() {
+                                if ui.input(|input| input.modifiers.shift) {
+                                    let current = editor_state.current_frame;
+                                    editor_state.selected_frames =
+                                        Some((current.min(i), current.max(i)));
+                                } else {
+                                    editor_state.current_frame = i;
+                                    editor_state.selected_frames = None;
+                                }
                             };
 
-                            ui.label(format!(
-                                "[{}]",
-                                editor_state.current_animation.timeline.frames[i].delay
-                            ));
+                            let (rect, response) = ui.allocate_exact_size(
+                                egui::Vec2::new(30.0, 4.0),
+                                egui::Sense::hover(),
+                            );
+                            if let Some(tag) = editor_state
+                                .current_animation
+                                .tags
+                                .iter()
+                                .find(|tag| (tag.start..=tag.end).contains(&i))
+                            {
+                                let [r, g, b] = tag.color;
+                                ui.painter().rect_filled(
+                                    rect,
+                                    0.0,
+                                    egui::Color32::from_rgb(r, g, b),
+                                );
+                                response.on_hover_text(&tag.name);
+                            }
+
+                            let frame = &editor_state.current_animation.timeline.frames[i];
+                            let (old_delay, repeat) = (frame.delay, frame.repeat);
+                            let mut delay = old_delay;
+                            ui.horizontal(|ui| {
+                                ui.spacing_mut().item_spacing.x = 1.0;
+                                let response = ui
+                                    .add(egui::DragValue::new(&mut delay).speed(0.1))
+                                    .on_hover_text("Delay in ticks; drag or click to edit");
+                                if response.drag_started() {
+                                    editor_state.end_action_merge();
+                                }
+                                if delay != old_delay {
+                                    let action = Action::ChangeDelay {
+                                        index: i,
+                                        from: old_delay,
+                                        to: delay,
+                                    };
+                                    if response.dragged() {
+                                        editor_state.do_action_merging(action);
+                                    } else {
+                                        editor_state.do_action(action);
+                                    }
+                                }
+                                if repeat > 1 {
+                                    ui.label(format!("×{repeat}"));
+                                }
+                            });
+                            let frame = &editor_state.current_animation.timeline.frames[i];
+                            if !frame.note.is_empty() {
+                                ui.label("📝").on_hover_text(&frame.note);
+                            }
+
+                            let has_root_motion = frame.root_motion != Vec2::ZERO;
+                            let has_offset = frame.offset != Vec2::ZERO;
+                            if has_root_motion || has_offset {
+                                ui.horizontal(|ui| {
+                                    ui.spacing_mut().item_spacing.x = 2.0;
+                                    if has_root_motion {
+                                        let Vec2 { x, y } = frame.root_motion;
+                                        marker_dot(ui, egui::Color32::YELLOW)
+                                            .on_hover_text(format!("Root motion: {x}, {y}"));
+                                    }
+                                    if has_offset {
+                                        let Vec2 { x, y } = frame.offset;
+                                        marker_dot(ui, egui::Color32::LIGHT_BLUE)
+                                            .on_hover_text(format!("Offset: {x}, {y}"));
+                                    }
+                                });
+                            }
                         },
                     );
+                    response.response.context_menu(|ui| {
+                        timeline_frame_menu(editor_state, ui, i, &mut frame_action);
+                    });
                 }
             });
         });
     });
+
+    if let Some(action) = frame_action {
+        editor_state.do_action(action);
+    }
+}
+
+fn timeline_frame_menu(
+    editor_state: &mut EditorState,
+    ui: &mut egui::Ui,
+    index: usize,
+    frame_action: &mut Option<Action>,
+) {
+    ui.set_enabled(editor_state.interaction_lock.allows_canvas());
+    let frame = &editor_state.current_animation.timeline.frames[index];
+    let old_delay = frame.delay;
+    let mut delay = old_delay;
+    ui.horizontal(|ui| {
+        ui.label("Delay");
+        let response = ui.add(egui::DragValue::new(&mut delay).speed(0.1));
+        if response.drag_started() {
+            editor_state.end_action_merge();
+        }
+        if delay != old_delay {
+            let action = Action::ChangeDelay {
+                index,
+                from: old_delay,
+                to: delay,
+            };
+            if response.dragged() {
+                editor_state.do_action_merging(action);
+            } else {
+                editor_state.do_action(action);
+            }
+        }
+    });
+    ui.separator();
+    if ui.button("Duplicate").clicked() {
+        *frame_action = Some(Action::DuplicateFrame { index });
+        ui.close_menu();
+    }
+    if ui.button("Insert placeholder before").clicked() {
+        editor_state
+            .command_queue
+            .push(EditorCommand::InsertPlaceholderFrame(index));
+        ui.close_menu();
+    }
+    if ui.button("Insert placeholder after").clicked() {
+        editor_state
+            .command_queue
+            .push(EditorCommand::InsertPlaceholderFrame(index + 1));
+        ui.close_menu();
+    }
+    ui.separator();
+    if ui.button("Delete").clicked() {
+        *frame_action = Some(Action::RemoveFrame {
+            frame: editor_state.current_animation.timeline.frames[index].clone(),
+            index,
+            tags: editor_state.current_animation.tags.clone(),
+        });
+        ui.close_menu();
+    }
 }
 
+/// A small colored dot marking a property of a timeline frame.
+fn marker_dot(ui: &mut egui::Ui, color: egui::Color32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(8.0), egui::Sense::hover());
+    ui.painter().circle_filled(rect.center(), 3.0, color);
+    response
+}
+
+fn frame_tooltip(ui: &mut egui::Ui, frame: &Frame) {
+    egui::Grid::new("frame_tooltip")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Delay");
+            ui.label(frame.delay.to_string());
+            ui.end_row();
+
+            ui.label("Repeat");
+            ui.label(frame.repeat.to_string());
+            ui.end_row();
+
+            ui.label("Offset");
+            ui.label(format!("{}, {}", frame.offset.x, frame.offset.y));
+            ui.end_row();
+
+            ui.label("Root motion");
+            ui.label(format!("{}, {}", frame.root_motion.x, frame.root_motion.y));
+            ui.end_row();
+
+            ui.label("Hitboxes");
+            let enabled = frame.hitboxes.values().filter(|hp| hp.enabled).count();
+            ui.label(enabled.to_string());
+            ui.end_row();
+        });
+}
+
+/// Square sizes offered as one-click presets for hitboxes.
+const HITBOX_SIZE_PRESETS: [f32; 3] = [8.0, 16.0, 32.0];
+
 #[derive(Resource, Default)]
 pub struct UiState {
     pub(crate) show_save_menu: bool,
     pub(crate) save_menu_unlock_on_non_cancel: bool,
     frame_delay: Cached<usize>,
+    frame_repeat: Cached<u32>,
+    frame_note: Cached<String>,
     frame_offset_x: Cached<f32>,
     frame_offset_y: Cached<f32>,
+    frame_scale_x: Cached<f32>,
+    frame_scale_y: Cached<f32>,
+    frame_rotation: Cached<f32>,
     motion_offset_x: Cached<f32>,
     motion_offset_y: Cached<f32>,
+    /// The frame the side panel's fields show. It only follows the current frame while none
+    /// of them is being typed in, so an edit is never applied to or overwritten by another
+    /// frame.
+    panel_frame: usize,
     hitboxes: HashMap<usize, HitboxUiState>,
+    /// Tags belong to the hitbox rather than a frame, so they are kept for every hitbox.
+    hitbox_tags: HashMap<usize, Cached<String>>,
+    /// Template typed in the batch rename window, open while `Some`.
+    batch_rename: Option<String>,
+    toasts: Vec<Toast>,
+    /// Position in the hitbox order of the entry being dragged in the side panel.
+    dragged_hitbox: Option<usize>,
+    /// Motion conversion awaiting confirmation, and whether it applies to all frames.
+    pending_motion_bake: Option<(MotionBake, bool)>,
+    /// The current frame the timeline last scrolled into view.
+    timeline_followed_frame: Option<usize>,
+    /// How many frames "Add placeholders" appends.
+    placeholder_count: usize,
+    /// The selected hitbox the side panel last scrolled into view.
+    followed_hitbox: Option<usize>,
+    command_palette: Option<CommandPalette>,
+    show_shortcuts: bool,
+    /// Name typed for a new workspace preset.
+    preset_name: String,
+    trim_comparison: Option<TrimComparison>,
+    /// Whether a text field had keyboard focus at the start of the last UI pass.
+    text_field_focused: bool,
+    /// Commands waiting for the focused text field to commit its edit; see
+    /// [`UiState::run_after_edits_commit`].
+    commands_after_commit: Vec<EditorCommand>,
+    /// Results of the last hitbox check, shown while `Some`.
+    hitbox_issues: Option<Vec<HitboxIssue>>,
+    /// A write that would replace a file the save dialog didn't ask about.
+    pending_overwrite: Option<PendingWrite>,
+    show_missing_images: bool,
+}
+
+impl UiState {
+    /// Asks before `write` replaces an existing file.
+    pub(crate) fn confirm_overwrite(&mut self, write: PendingWrite) {
+        self.pending_overwrite = Some(write);
+    }
+
+    /// Closes the save prompt without running the action it was opened for.
+    pub(crate) fn cancel_save_menu(&mut self, editor_state: &mut EditorState) {
+        editor_state.action_after_save = None;
+        self.show_save_menu = false;
+        editor_state.interaction_lock.release();
+    }
+
+    pub(crate) fn open_command_palette(&mut self) {
+        self.command_palette = Some(default());
+    }
+
+    pub(crate) fn toggle_shortcuts(&mut self) {
+        self.show_shortcuts = !self.show_shortcuts;
+    }
+
+    pub(crate) fn show_missing_images(&mut self) {
+        self.show_missing_images = true;
+    }
+
+    pub(crate) fn show_hitbox_issues(&mut self, issues: Vec<HitboxIssue>) {
+        self.hitbox_issues = Some(issues);
+    }
+
+    pub(crate) fn text_field_focused(&self) -> bool {
+        self.text_field_focused
+    }
+
+    /// Queues `command` again once the focused text field has committed what is typed in it,
+    /// so that a save started from a shortcut doesn't miss it.
+    pub(crate) fn run_after_edits_commit(&mut self, command: EditorCommand) {
+        self.commands_after_commit.push(command);
+    }
+
+    fn editing_panel_field(&self) -> bool {
+        [
+            self.frame_delay.editing,
+            self.frame_repeat.editing,
+            self.frame_note.editing,
+            self.frame_offset_x.editing,
+            self.frame_offset_y.editing,
+            self.frame_scale_x.editing,
+            self.frame_scale_y.editing,
+            self.frame_rotation.editing,
+            self.motion_offset_x.editing,
+            self.motion_offset_y.editing,
+        ]
+        .into_iter()
+        .chain(self.hitboxes.values().flat_map(|hitbox| {
+            [
+                hitbox.desc.editing,
+                hitbox.x.editing,
+                hitbox.y.editing,
+                hitbox.width.editing,
+                hitbox.height.editing,
+            ]
+        }))
+        .any(|editing| editing)
+    }
+
+    pub(crate) fn toast(&mut self, message: impl Into<String>) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            remaining: 4.0,
+        });
+    }
+}
+
+/// Textures of the trim comparison, rebuilt when the frame or its trim changes. A side is
+/// `None` when it has no pixels.
+struct TrimComparison {
+    key: (HandleId, [u32; 4], [u32; 4]),
+    drawn: Option<egui::TextureHandle>,
+    saved: Option<egui::TextureHandle>,
+}
+
+struct Toast {
+    message: String,
+    remaining: f32,
 }
 
 #[derive(Default)]
@@ -175,6 +2072,8 @@ struct HitboxUiState {
 struct Cached<T> {
     cache: T,
     val: String,
+    /// Whether the field had keyboard focus the last time it was shown.
+    editing: bool,
 }
 
 impl<T: Default + ToString> Default for Cached<T> {
@@ -183,6 +2082,7 @@ impl<T: Default + ToString> Default for Cached<T> {
         Self {
             val: cache.to_string(),
             cache,
+            editing: false,
         }
     }
 }
@@ -197,24 +2097,49 @@ impl<T: ToString + PartialEq + Clone> Cached<T> {
 }
 
 fn update_ui_state(editor_state: Res<EditorState>, mut ui_state: ResMut<UiState>) {
-    if let Some(frame) = editor_state.get_frame(editor_state.current_frame) {
+    let hitboxes = &editor_state.current_animation.hitboxes;
+    ui_state
+        .hitbox_tags
+        .retain(|id, _| hitboxes.contains_key(id));
+    for (id, hitbox) in hitboxes {
+        ui_state
+            .hitbox_tags
+            .entry(*id)
+            .or_default()
+            .update(&hitbox.tag);
+    }
+    // Entries are kept for every hitbox, so its description can be edited from any frame. The
+    // position and size are only shown, and updated, where the box is on the frame.
+    ui_state.hitboxes.retain(|id, _| hitboxes.contains_key(id));
+    for (id, hitbox) in hitboxes {
+        ui_state
+            .hitboxes
+            .entry(*id)
+            .or_default()
+            .desc
+            .update(&hitbox.desc);
+    }
+
+    if !ui_state.editing_panel_field() || editor_state.get_frame(ui_state.panel_frame).is_none() {
+        ui_state.panel_frame = editor_state.current_frame;
+    }
+
+    if let Some(frame) = editor_state.get_frame(ui_state.panel_frame) {
         ui_state.frame_delay.update(&frame.delay);
+        ui_state.frame_repeat.update(&frame.repeat);
+        ui_state.frame_note.update(&frame.note);
         ui_state.frame_offset_x.update(&frame.offset.x);
         ui_state.frame_offset_y.update(&frame.offset.y);
+        ui_state.frame_scale_x.update(&frame.scale.x);
+        ui_state.frame_scale_y.update(&frame.scale.y);
+        ui_state.frame_rotation.update(&frame.rotation);
         ui_state.motion_offset_x.update(&frame.root_motion.x);
         ui_state.motion_offset_y.update(&frame.root_motion.y);
 
-        ui_state
-            .hitboxes
-            .drain_filter(|k, _| !frame.get_hitbox(*k).is_some_and(|hb| hb.enabled));
         for (k, v) in frame.hitboxes.iter() {
-            if !ui_state.hitboxes.contains_key(k) {
-                ui_state.hitboxes.insert(*k, default());
-            }
-
-            let w = ui_state.hitboxes.get_mut(k).unwrap();
-            w.desc
-                .update(&editor_state.current_animation.hitboxes.get(k).unwrap().desc);
+            let Some(w) = ui_state.hitboxes.get_mut(k) else {
+                continue;
+            };
             w.x.update(&v.pos.x);
             w.y.update(&v.pos.y);
             w.width.update(&v.size.x);
@@ -228,12 +2153,12 @@ fn cached_property_textbox<T: ToString + FromStr>(
     property: &mut Cached<T>,
     action: impl FnOnce(&T, T),
 ) {
-    if ui
-        .add(
-            egui::TextEdit::singleline(&mut property.val).min_size(egui::Vec2::new(50.0, 0.0)), // .desired_width(50.0),
-        )
-        .lost_focus()
-    {
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut property.val).min_size(egui::Vec2::new(50.0, 0.0)), // .desired_width(50.0),
+    );
+    property.editing = response.has_focus();
+    // Enter ends focus on a single line field, so it commits too.
+    if response.lost_focus() {
         if let Ok(new_val) = property.val.parse::<T>() {
             action(&property.cache, new_val);
         }
@@ -241,7 +2166,7 @@ fn cached_property_textbox<T: ToString + FromStr>(
 }
 
 fn frame_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut egui::Ui) {
-    let current_frame = editor_state.current_frame;
+    let current_frame = ui_state.panel_frame;
     if editor_state.get_frame(current_frame).is_none() {
         return;
     }
@@ -252,11 +2177,36 @@ fn frame_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut e
         ui.end_row();
 
         ui.label("Duration");
-        cached_property_textbox(ui, &mut ui_state.frame_delay, |old_delay, new_delay| {
-            editor_state.do_action(Action::ChangeDelay {
+        ui.horizontal(|ui| {
+            cached_property_textbox(ui, &mut ui_state.frame_delay, |old_delay, new_delay| {
+                editor_state.do_action(Action::ChangeDelay {
+                    index: current_frame,
+                    from: *old_delay,
+                    to: new_delay,
+                });
+            });
+            let delay = editor_state.frame(current_frame).delay;
+            let millis = editor_state.current_animation.ticks_to_millis(delay);
+            ui.weak(format!("{millis:.0} ms"));
+        });
+        ui.end_row();
+
+        ui.label("Repeat");
+        cached_property_textbox(ui, &mut ui_state.frame_repeat, |old_repeat, new_repeat| {
+            editor_state.do_action(Action::ChangeRepeat {
+                index: current_frame,
+                from: *old_repeat,
+                to: new_repeat.max(1),
+            });
+        });
+        ui.end_row();
+
+        ui.label("Note");
+        cached_property_textbox(ui, &mut ui_state.frame_note, |old_note, new_note| {
+            editor_state.do_action(Action::ChangeNote {
                 index: current_frame,
-                from: *old_delay,
-                to: new_delay,
+                from: old_note.clone(),
+                to: new_note,
             });
         });
         ui.end_row();
@@ -288,7 +2238,89 @@ fn frame_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut e
                     });
                 });
                 ui.end_row();
+
+                if editor_state.show_trim_preview
+                    && let Some(preview) = editor_state.trim_preview
+                {
+                    ui.label("Saved:");
+                    ui.label(format!("{}, {}", preview.offset.x, preview.offset.y))
+                        .on_hover_text("The offset within the trimmed cell after the next save");
+                    ui.end_row();
+                }
+            });
+        ui.end_row();
+
+        if editor_state.show_saved_cell
+            && let Some(preview) = editor_state.trim_preview
+        {
+            let [left, top, right, bottom] = preview.padding;
+            ui.label("Cell padding");
+            ui.label(format!("L {left}, T {top}, R {right}, B {bottom}"))
+                .on_hover_text("Empty space this frame will carry into the atlas cell");
+            ui.end_row();
+        }
+
+        ui.label("Scale");
+
+        egui::Grid::new("scale_grid")
+            .num_columns(2)
+            .min_col_width(0.0)
+            .show(ui, |ui| {
+                ui.label("X:");
+                cached_property_textbox(ui, &mut ui_state.frame_scale_x, |_, new_x| {
+                    let cur_scale = editor_state.frame(current_frame).scale;
+                    if new_x.is_finite() && new_x != 0.0 {
+                        editor_state.do_action(Action::ScaleSprite {
+                            frame_index: current_frame,
+                            from: cur_scale,
+                            to: Vec2::new(new_x, cur_scale.y),
+                        });
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Y:");
+                cached_property_textbox(ui, &mut ui_state.frame_scale_y, |_, new_y| {
+                    let cur_scale = editor_state.frame(current_frame).scale;
+                    if new_y.is_finite() && new_y != 0.0 {
+                        editor_state.do_action(Action::ScaleSprite {
+                            frame_index: current_frame,
+                            from: cur_scale,
+                            to: Vec2::new(cur_scale.x, new_y),
+                        });
+                    }
+                });
+                ui.end_row();
+            });
+        ui.end_row();
+
+        ui.label("Rotation")
+            .on_hover_text("Degrees, counterclockwise. Hitboxes are not rotated");
+        cached_property_textbox(
+            ui,
+            &mut ui_state.frame_rotation,
+            |old_rotation, new_rotation| {
+                editor_state.do_action(Action::RotateSprite {
+                    frame_index: current_frame,
+                    from: *old_rotation,
+                    to: new_rotation,
+                });
+            },
+        );
+        ui.end_row();
+
+        ui.label("Flip");
+        ui.horizontal(|ui| {
+            let frame = editor_state.frame(current_frame);
+            let (mut flip_x, mut flip_y) = (frame.flip_x, frame.flip_y);
+            let x = ui.checkbox(&mut flip_x, "X").changed();
+            let y = ui.checkbox(&mut flip_y, "Y").changed();
+            editor_state.do_action(Action::ToggleFlip {
+                frame_index: current_frame,
+                x,
+                y,
             });
+        });
         ui.end_row();
 
         ui.label("Root motion");
@@ -328,7 +2360,7 @@ fn frame_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut e
                     b: current_frame - 1,
                 };
                 editor_state.do_action(action);
-                editor_state.current_frame -= 1;
+                editor_state.current_frame = current_frame - 1;
             };
         });
         ui.add_enabled_ui(
@@ -340,7 +2372,7 @@ fn frame_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut e
                         b: current_frame + 1,
                     };
                     editor_state.do_action(action);
-                    editor_state.current_frame += 1;
+                    editor_state.current_frame = current_frame + 1;
                 };
             },
         );
@@ -348,35 +2380,231 @@ fn frame_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut e
     });
 }
 
-fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut egui::Ui) {
-    if ui.button("Create hitbox").clicked() {
-        let mut id = 0;
-        while editor_state.current_animation.hitboxes.contains_key(&id) {
-            id += 1;
+fn metadata(editor_state: &mut EditorState, ui: &mut egui::Ui) {
+    ui.collapsing("Metadata", |ui| {
+        let metadata = &mut editor_state.current_animation.metadata;
+        let mut changed = false;
+
+        egui::Grid::new("metadata").num_columns(2).show(ui, |ui| {
+            ui.label("Name");
+            changed |= ui.text_edit_singleline(&mut metadata.name).changed();
+            ui.end_row();
+
+            ui.label("Author");
+            changed |= ui.text_edit_singleline(&mut metadata.author).changed();
+            ui.end_row();
+
+            ui.label("Coordinates").on_hover_text(
+                "Which way y points in saved and exported hitbox positions and root motion",
+            );
+            let y_axis = &mut editor_state.current_animation.y_axis;
+            egui::ComboBox::from_id_source("y_axis")
+                .selected_text(y_axis.label())
+                .show_ui(ui, |ui| {
+                    for option in [YAxis::Up, YAxis::Down] {
+                        changed |= ui
+                            .selectable_value(y_axis, option, option.label())
+                            .changed();
+                    }
+                });
+            ui.end_row();
+        });
+
+        ui.label("Tags");
+        let mut remove = None;
+        for (i, tag) in metadata.tags.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(egui::TextEdit::singleline(tag).desired_width(120.0))
+                    .changed();
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
         }
 
-        let action = Action::CreateHitbox {
-            id,
-            desc: format!("Hitbox {id}"),
+        if let Some(i) = remove {
+            metadata.tags.remove(i);
+            changed = true;
+        }
+
+        if ui.button("Add tag").clicked() {
+            metadata.tags.push(String::new());
+            changed = true;
+        }
+
+        if changed {
+            editor_state.edit_outside_history();
+        }
+    });
+}
+
+fn frame_tags(editor_state: &mut EditorState, ui: &mut egui::Ui) {
+    let current_frame = editor_state.current_frame;
+    if editor_state.get_frame(current_frame).is_none() {
+        return;
+    }
+
+    ui.collapsing("Frame tags", |ui| {
+        let mut tags = editor_state.current_animation.tags.clone();
+        // Typing a name or dragging a color is one undo step; buttons are one each.
+        let mut edited = false;
+        let mut changed = false;
+        let mut remove = None;
+
+        for (i, tag) in tags.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let color = ui.color_edit_button_srgb(&mut tag.color);
+                if color.clicked() {
+                    editor_state.end_action_merge();
+                }
+                edited |= color.changed();
+                let name = ui.add(egui::TextEdit::singleline(&mut tag.name).desired_width(80.0));
+                if name.gained_focus() {
+                    editor_state.end_action_merge();
+                }
+                edited |= name.changed();
+                ui.label(format!("{}-{}", tag.start + 1, tag.end + 1));
+                if ui.button("Start here").clicked() {
+                    tag.start = current_frame;
+                    tag.end = tag.end.max(current_frame);
+                    changed = true;
+                }
+                if ui.button("End here").clicked() {
+                    tag.end = current_frame;
+                    tag.start = tag.start.min(current_frame);
+                    changed = true;
+                }
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+
+        if let Some(i) = remove {
+            tags.remove(i);
+            changed = true;
+        }
+
+        if ui.button("Add tag").clicked() {
+            let name = format!("Tag {}", tags.len() + 1);
+            tags.push(FrameTag {
+                name,
+                color: [255, 160, 0],
+                start: current_frame,
+                end: current_frame,
+            });
+            changed = true;
+        }
+
+        let action = Action::SetFrameTags {
+            from: editor_state.current_animation.tags.clone(),
+            to: tags,
         };
-        editor_state.do_action(action);
+        if changed {
+            editor_state.do_action(action);
+        } else if edited {
+            editor_state.do_action_merging(action);
+        }
+    });
+}
+
+fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut egui::Ui) {
+    let panel_frame = ui_state.panel_frame;
+    let mut anchor_relative = editor_state.current_animation.anchor_relative_hitboxes;
+    if ui
+        .checkbox(&mut anchor_relative, "Hitboxes relative to anchor")
+        .on_hover_text("Store hitbox positions relative to each frame's anchor, so moving the anchor moves the boxes with the sprite")
+        .changed()
+    {
+        editor_state.do_action(Action::SetAnchorRelativeHitboxes {
+            enabled: anchor_relative,
+        });
+    }
+
+    let mut scale_hitboxes = editor_state.current_animation.scale_hitboxes;
+    if ui
+        .checkbox(&mut scale_hitboxes, "Scale hitboxes with frame")
+        .on_hover_text("Scale hitboxes around the anchor by each frame's scale")
+        .changed()
+    {
+        editor_state.do_action(Action::SetScaleHitboxes {
+            enabled: scale_hitboxes,
+        });
     }
 
+    ui.horizontal(|ui| {
+        if ui.button("Create hitbox").clicked() {
+            let frame_index = editor_state.current_frame;
+            if editor_state.ask_hitbox_size && editor_state.get_frame(frame_index).is_some() {
+                let size = editor_state.last_hitbox_size;
+                let origin = editor_state
+                    .frame(frame_index)
+                    .hitbox_origin(editor_state.current_animation.anchor_relative_hitboxes);
+                editor_state.begin_hitbox_creation(
+                    frame_index,
+                    size * Vec2::new(-0.5, 0.5) - origin,
+                    size,
+                    None,
+                );
+            } else {
+                let id = editor_state.next_hitbox_id();
+
+                let action = Action::CreateHitbox {
+                    id,
+                    desc: format!("Hitbox {id}"),
+                };
+                editor_state.do_action(action);
+            }
+        }
+        ui.checkbox(&mut editor_state.ask_hitbox_size, "Ask for size")
+            .on_hover_text("Type the exact position and size of new hitboxes");
+    });
+
     let mut enable = vec![];
     let mut disable = vec![];
+    let mut delete = None;
 
-    for hitbox in editor_state.current_animation.hitboxes.clone().values() {
-        let mut header = egui::RichText::new(&hitbox.desc);
+    let mut header_rects = vec![];
+
+    for &id in &editor_state.current_animation.hitbox_order.clone() {
+        let hitbox = editor_state.current_animation.hitboxes[&id].clone();
+        let mut header = egui::RichText::new(format!(
+            "{} ({})",
+            hitbox.desc,
+            hitbox.kind.label().to_lowercase()
+        ));
 
         let is_enabled = editor_state
-            .get_frame(editor_state.current_frame)
+            .get_frame(panel_frame)
             .is_some_and(|f| f.is_hitbox_enabled(hitbox.id));
 
         if !is_enabled {
             header = header.strikethrough();
         }
+        let is_selected = editor_state.currently_selected_box == Some(hitbox.id);
+        if is_selected {
+            header = header.strong();
+        }
 
-        ui.collapsing(header, |ui| {
+        let state = egui::collapsing_header::CollapsingState::load_with_default_open(
+            ui.ctx(),
+            ui.make_persistent_id(("hitbox", hitbox.id)),
+            false,
+        );
+        let header_response = state.show_header(ui, |ui| {
+            ui.add(egui::Label::new(header).sense(egui::Sense::drag()))
+                .on_hover_text("Drag to reorder")
+        });
+        if header_response.inner.drag_started() {
+            ui_state.dragged_hitbox = Some(header_rects.len());
+        }
+        if is_selected && ui_state.followed_hitbox != Some(hitbox.id) {
+            header_response.response.scroll_to_me(None);
+        }
+        header_rects.push(header_response.response.rect);
+
+        header_response.body(|ui| {
             egui::Grid::new(format!("{}_grid", &hitbox.id))
                 .num_columns(2)
                 .show(ui, |ui| {
@@ -384,10 +2612,52 @@ fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut
                     ui.label(hitbox.id.to_string());
                     ui.end_row();
                     ui.label("Desc");
-                    ui.label(&hitbox.desc);
+                    if let Some(hitbox_ui) = ui_state.hitboxes.get_mut(&hitbox.id) {
+                        cached_property_textbox(
+                            ui,
+                            &mut hitbox_ui.desc,
+                            |old_desc, new_desc: String| {
+                                editor_state.do_action(Action::RenameHitbox {
+                                    id: hitbox.id,
+                                    from: old_desc.clone(),
+                                    to: new_desc.trim().to_string(),
+                                });
+                            },
+                        );
+                    }
+                    ui.end_row();
+                    ui.label("Kind");
+                    let mut kind = hitbox.kind;
+                    egui::ComboBox::from_id_source(("hitbox_kind", hitbox.id))
+                        .selected_text(kind.label())
+                        .show_ui(ui, |ui| {
+                            for option in BoxKind::ALL {
+                                ui.selectable_value(&mut kind, option, option.label());
+                            }
+                        });
+                    if kind != hitbox.kind {
+                        editor_state.do_action(Action::SetHitboxKind {
+                            id: hitbox.id,
+                            from: hitbox.kind,
+                            to: kind,
+                        });
+                    }
+                    ui.end_row();
+                    ui.label("Tag").on_hover_text(
+                        "Free-form category exported for game logic, e.g. grab or projectile",
+                    );
+                    if let Some(tag) = ui_state.hitbox_tags.get_mut(&hitbox.id) {
+                        cached_property_textbox(ui, tag, |old_tag, new_tag: String| {
+                            editor_state.do_action(Action::SetHitboxTag {
+                                id: hitbox.id,
+                                from: old_tag.clone(),
+                                to: new_tag.trim().to_string(),
+                            });
+                        });
+                    }
                     ui.end_row();
 
-                    if editor_state.get_frame(editor_state.current_frame).is_some() {
+                    if editor_state.get_frame(panel_frame).is_some() {
                         ui.label("Enabled");
                         let mut b = is_enabled;
                         if ui.checkbox(&mut b, "").changed() {
@@ -403,7 +2673,7 @@ fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut
                         ui.end_row();
 
                         if is_enabled {
-                            let current_frame = editor_state.current_frame;
+                            let current_frame = panel_frame;
 
                             ui.label("Position");
 
@@ -452,6 +2722,21 @@ fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut
                                 });
                             ui.end_row();
 
+                            ui.label("Align");
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Center on anchor").clicked() {
+                                    editor_state.center_hitbox_on_anchor(current_frame, hitbox.id);
+                                }
+                                if ui
+                                    .small_button("Move anchor here")
+                                    .on_hover_text("Set the frame's offset to this box's center")
+                                    .clicked()
+                                {
+                                    editor_state.snap_anchor_to_hitbox(current_frame, hitbox.id);
+                                }
+                            });
+                            ui.end_row();
+
                             ui.label("Size");
 
                             egui::Grid::new(format!("{}_size_grid", &hitbox.id))
@@ -471,7 +2756,10 @@ fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut
                                                 frame_index: current_frame,
                                                 id: hitbox.id.clone(),
                                                 from: cur_size,
-                                                to: Vec2::new(new_x, cur_size.y),
+                                                to: Vec2::new(
+                                                    new_x.max(MIN_HITBOX_SIZE),
+                                                    cur_size.y,
+                                                ),
                                             });
                                         },
                                     );
@@ -491,73 +2779,110 @@ fn hitbox_info(editor_state: &mut EditorState, ui_state: &mut UiState, ui: &mut
                                                 frame_index: current_frame,
                                                 id: hitbox.id.clone(),
                                                 from: cur_size,
-                                                to: Vec2::new(cur_size.x, new_y),
+                                                to: Vec2::new(
+                                                    cur_size.x,
+                                                    new_y.max(MIN_HITBOX_SIZE),
+                                                ),
                                             });
                                         },
                                     );
                                     ui.end_row();
+
+                                    ui.label("");
+                                    ui.horizontal(|ui| {
+                                        let cur_size = editor_state
+                                            .frame(current_frame)
+                                            .hitbox(hitbox.id)
+                                            .size;
+                                        let mut new_size = None;
+                                        if ui
+                                            .small_button("Square")
+                                            .on_hover_text("Use the larger side for both")
+                                            .clicked()
+                                        {
+                                            new_size = Some(Vec2::splat(cur_size.max_element()));
+                                        }
+                                        for preset in HITBOX_SIZE_PRESETS {
+                                            if ui.small_button(preset.to_string()).clicked() {
+                                                new_size = Some(Vec2::splat(preset));
+                                            }
+                                        }
+                                        if let Some(to) = new_size {
+                                            editor_state.do_action(Action::ResizeHitbox {
+                                                frame_index: current_frame,
+                                                id: hitbox.id,
+                                                from: cur_size,
+                                                to,
+                                            });
+                                        }
+                                    });
+                                    ui.end_row();
                                 });
                             ui.end_row();
+
+                            if !editor_state
+                                .frame(current_frame)
+                                .hitbox(hitbox.id)
+                                .has_area()
+                            {
+                                ui.label("");
+                                ui.colored_label(
+                                    egui::Color32::RED,
+                                    "Zero or negative size, the box is invisible",
+                                );
+                                ui.end_row();
+                            }
                         }
                     }
-                })
+                });
+            if ui
+                .button("Delete hitbox")
+                .on_hover_text("Remove this hitbox from every frame")
+                .clicked()
+            {
+                delete = Some(hitbox.id);
+            }
         });
     }
 
-    for id in enable {
-        if let Some(hp) = editor_state
-            .frame(editor_state.current_frame)
-            .get_hitbox(id)
+    if let Some(from) = ui_state.dragged_hitbox {
+        if let Some(pointer) = ui.ctx().pointer_interact_pos()
+            && let Some(last) = header_rects.last()
+            && from < header_rects.len()
         {
-            println!("Hitbox position already found; enabling");
-            let action = Action::ToggleHitboxEnabled {
-                frame_index: editor_state.current_frame,
-                id: hp.id,
-            };
-            editor_state.do_action(action);
-        } else {
-            let last_pos = editor_state.current_animation.timeline.frames
-                [..editor_state.current_frame]
+            let to = header_rects
                 .iter()
-                .rev()
-                .find_map(|f| f.get_hitbox(id));
+                .filter(|rect| rect.center().y < pointer.y)
+                .count();
+            let y = header_rects
+                .get(to)
+                .map_or(last.bottom(), |rect| rect.top());
+            ui.painter()
+                .hline(ui.min_rect().x_range(), y, ui.visuals().selection.stroke);
 
-            let new_pos = if let Some(last_pos) = last_pos {
-                println!("Using hitbox position from earlier frame; enabling");
-                HitboxPos {
-                    id: id.clone(),
-                    pos: last_pos.pos,
-                    size: last_pos.size,
-                    enabled: false,
-                }
-            } else {
-                println!("Creating new hitbox position; enabling");
-                HitboxPos {
-                    id: id.clone(),
-                    pos: Vec2::new(-4.0, 4.0),
-                    size: Vec2::new(8.0, 8.0),
-                    enabled: false,
-                }
-            };
-
-            editor_state
-                .frame_mut(editor_state.current_frame)
-                .hitboxes
-                .insert(id, new_pos);
-
-            let action = Action::ToggleHitboxEnabled {
-                frame_index: editor_state.current_frame,
-                id,
-            };
-            editor_state.do_action(action);
+            if ui.input(|i| i.pointer.any_released()) {
+                let to = if to > from { to - 1 } else { to };
+                editor_state.do_action(Action::ReorderHitbox { from, to });
+            }
         }
+        if !ui.input(|i| i.pointer.any_down()) {
+            ui_state.dragged_hitbox = None;
+        }
+    }
+
+    for id in enable {
+        editor_state.enable_hitbox(panel_frame, id);
     }
 
     for id in disable {
         let action = Action::ToggleHitboxEnabled {
-            frame_index: editor_state.current_frame,
+            frame_index: panel_frame,
             id,
         };
         editor_state.do_action(action);
     }
+
+    if let Some(id) = delete {
+        editor_state.delete_hitbox(id);
+    }
 }